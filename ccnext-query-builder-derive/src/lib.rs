@@ -0,0 +1,308 @@
+//! `#[derive(CcnextQuery)]`, re-exported by `ccnext-query-builder`.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    parse::ParseStream, parse_macro_input, spanned::Spanned, Data, DeriveInput, Error, Fields,
+    Ident, LitInt, LitStr, Token,
+};
+
+/// Implements `CcnextQuery` for a struct with named fields, each annotated with where its
+/// value comes from. Every field selects exactly one segment, in declaration order:
+///
+/// - `#[field(RxStatus)]`: a static field, any `QueryableFields` variant.
+/// - `#[function("burn", arg = "value")]` or `#[function("burn", signature)]`: calldata.
+/// - `#[event("Transfer", arg = "to")]`, `#[event("Transfer", signature)]` or
+///   `#[event("Transfer", address)]`: a log. `log_index = 1` narrows the logs down and
+///   `first` takes the first match instead of failing when several logs match.
+///
+/// Functions and events are given by name, `0x` selector or signature, resolved through the
+/// ABI provider, or as a human-readable fragment (`"event Transfer(...)"`) that needs none.
+#[proc_macro_derive(CcnextQuery, attributes(field, function, event))]
+pub fn derive_ccnext_query(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+enum Selection {
+    Argument(LitStr),
+    Signature,
+    Address,
+}
+
+enum Source {
+    Field(Ident),
+    Function {
+        name: LitStr,
+        selection: Selection,
+    },
+    Event {
+        name: LitStr,
+        selection: Selection,
+        log_index: Option<LitInt>,
+        first: bool,
+    },
+}
+
+struct QueryField {
+    ident: Ident,
+    source: Source,
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(Error::new(
+                    input.span(),
+                    "CcnextQuery needs a struct with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(Error::new(
+                input.span(),
+                "CcnextQuery can only be derived for structs",
+            ))
+        }
+    };
+
+    let mut query_fields = Vec::new();
+    for field in fields {
+        let ident = field.ident.clone().expect("named field");
+        let mut source = None;
+        for attr in &field.attrs {
+            let parsed = if attr.path().is_ident("field") {
+                Source::Field(attr.parse_args()?)
+            } else if attr.path().is_ident("function") {
+                attr.parse_args_with(parse_function)?
+            } else if attr.path().is_ident("event") {
+                attr.parse_args_with(parse_event)?
+            } else {
+                continue;
+            };
+
+            if source.replace(parsed).is_some() {
+                return Err(Error::new(
+                    attr.span(),
+                    "a field can only have one of #[field], #[function] or #[event]",
+                ));
+            }
+        }
+
+        match source {
+            Some(source) => query_fields.push(QueryField { ident, source }),
+            None => {
+                return Err(Error::new(
+                    field.span(),
+                    "missing #[field(...)], #[function(...)] or #[event(...)]",
+                ))
+            }
+        }
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let steps = query_fields.iter().map(build_step);
+    let field_count = query_fields.len();
+    let decoded_fields = query_fields.iter().enumerate().map(|(index, field)| {
+        let ident = &field.ident;
+        let ident_name = ident.to_string();
+        quote! {
+            #ident: ::ccnext_query_builder::abi::ccnext_query::decode_segment(
+                encoded,
+                segments[#index],
+                #ident_name,
+            )?
+        }
+    });
+
+    Ok(quote! {
+        impl #impl_generics ::ccnext_query_builder::abi::ccnext_query::CcnextQuery
+            for #name #ty_generics #where_clause
+        {
+            fn build_query(
+                query_builder: &mut ::ccnext_query_builder::abi::query_builder::QueryBuilder,
+            ) -> impl ::core::future::Future<
+                Output = ::core::result::Result<
+                    ::std::vec::Vec<(usize, usize)>,
+                    ::ccnext_query_builder::abi::models::QueryBuilderError,
+                >,
+            > + ::core::marker::Send {
+                async move {
+                    let first_segment = query_builder.get_selected_offsets().len();
+                    #(#steps)*
+                    Ok(query_builder.get_selected_offsets()[first_segment..].to_vec())
+                }
+            }
+
+            fn decode_query(
+                encoded: &[u8],
+                segments: &[(usize, usize)],
+            ) -> ::core::result::Result<Self, ::ccnext_query_builder::abi::models::QueryBuilderError>
+            {
+                if segments.len() != #field_count {
+                    return Err(
+                        ::ccnext_query_builder::abi::models::QueryBuilderError::MissMatchedLengthDecoding,
+                    );
+                }
+                Ok(Self {
+                    #(#decoded_fields,)*
+                })
+            }
+        }
+    })
+}
+
+fn parse_function(input: ParseStream) -> syn::Result<Source> {
+    let name: LitStr = input.parse()?;
+    let mut selection = None;
+    while !input.is_empty() {
+        input.parse::<Token![,]>()?;
+        let key: Ident = input.parse()?;
+        match key.to_string().as_str() {
+            "arg" => {
+                input.parse::<Token![=]>()?;
+                set_selection(&mut selection, &key, Selection::Argument(input.parse()?))?;
+            }
+            "signature" => set_selection(&mut selection, &key, Selection::Signature)?,
+            _ => {
+                return Err(Error::new(
+                    key.span(),
+                    "expected `arg = \"...\"` or `signature`",
+                ))
+            }
+        }
+    }
+
+    match selection {
+        Some(selection) => Ok(Source::Function { name, selection }),
+        None => Err(Error::new(
+            name.span(),
+            "select `arg = \"...\"` or `signature` of the function",
+        )),
+    }
+}
+
+fn parse_event(input: ParseStream) -> syn::Result<Source> {
+    let name: LitStr = input.parse()?;
+    let mut selection = None;
+    let mut log_index = None;
+    let mut first = false;
+    while !input.is_empty() {
+        input.parse::<Token![,]>()?;
+        let key: Ident = input.parse()?;
+        match key.to_string().as_str() {
+            "arg" => {
+                input.parse::<Token![=]>()?;
+                set_selection(&mut selection, &key, Selection::Argument(input.parse()?))?;
+            }
+            "signature" => set_selection(&mut selection, &key, Selection::Signature)?,
+            "address" => set_selection(&mut selection, &key, Selection::Address)?,
+            "log_index" => {
+                input.parse::<Token![=]>()?;
+                log_index = Some(input.parse()?);
+            }
+            "first" => first = true,
+            _ => return Err(Error::new(
+                key.span(),
+                "expected `arg = \"...\"`, `signature`, `address`, `log_index = ...` or `first`",
+            )),
+        }
+    }
+
+    match selection {
+        Some(selection) => Ok(Source::Event {
+            name,
+            selection,
+            log_index,
+            first,
+        }),
+        None => Err(Error::new(
+            name.span(),
+            "select `arg = \"...\"`, `signature` or `address` of the event",
+        )),
+    }
+}
+
+fn set_selection(
+    selection: &mut Option<Selection>,
+    key: &Ident,
+    value: Selection,
+) -> syn::Result<()> {
+    if selection.replace(value).is_some() {
+        return Err(Error::new(
+            key.span(),
+            "each field selects exactly one segment",
+        ));
+    }
+    Ok(())
+}
+
+fn build_step(field: &QueryField) -> TokenStream2 {
+    match &field.source {
+        Source::Field(queryable_field) => quote! {
+            query_builder.add_static_field(
+                ::ccnext_query_builder::abi::models::QueryableFields::#queryable_field,
+            )?;
+        },
+        Source::Function { name, selection } => {
+            let configure = match selection {
+                Selection::Argument(argument) => {
+                    quote! { builder.add_argument(#argument.into())?; }
+                }
+                _ => quote! { builder.add_signature()?; },
+            };
+            let configurator = quote! {
+                |builder| {
+                    #configure
+                    Ok(())
+                }
+            };
+
+            if name.value().starts_with("function ") {
+                quote! { query_builder.function_builder_with_abi(#name, #configurator)?; }
+            } else {
+                quote! { query_builder.function_builder(#name.into(), #configurator).await?; }
+            }
+        }
+        Source::Event {
+            name,
+            selection,
+            log_index,
+            first,
+        } => {
+            let configure = match selection {
+                Selection::Argument(argument) => quote! { builder.add_argument(#argument)?; },
+                Selection::Signature => quote! { builder.add_signature()?; },
+                Selection::Address => quote! { builder.add_address()?; },
+            };
+            let configurator = quote! {
+                |builder| {
+                    #configure
+                    Ok(())
+                }
+            };
+            let filter = match log_index {
+                Some(expected) => quote! { |_log, _event, log_index| log_index == #expected },
+                None => quote! { |_log, _event, _log_index| true },
+            };
+
+            if name.value().starts_with("event ") {
+                quote! {
+                    query_builder.event_builder_with_abi(#name, #filter, #first, #configurator)?;
+                }
+            } else {
+                quote! {
+                    query_builder
+                        .event_builder(#name.into(), #filter, #first, #configurator)
+                        .await?;
+                }
+            }
+        }
+    }
+}
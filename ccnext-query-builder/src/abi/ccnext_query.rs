@@ -0,0 +1,112 @@
+use std::future::Future;
+
+use alloy::primitives::{Address, Bytes, FixedBytes, I256, U256};
+
+use super::{models::QueryBuilderError, query_builder::QueryBuilder};
+
+/// A fixed query shape declared as a struct, usually through `#[derive(CcnextQuery)]`.
+pub trait CcnextQuery: Sized {
+    /// Adds the selections of this query to `query_builder` and returns the segments they
+    /// produced, one per struct field.
+    fn build_query(
+        query_builder: &mut QueryBuilder,
+    ) -> impl Future<Output = Result<Vec<(usize, usize)>, QueryBuilderError>> + Send;
+
+    /// Fills the struct back from the abi-encoded transaction and receipt, given the
+    /// segments returned by [`Self::build_query`].
+    fn decode_query(encoded: &[u8], segments: &[(usize, usize)])
+        -> Result<Self, QueryBuilderError>;
+}
+
+/// Values a single selected segment can be read back as. Word sized types expect the
+/// 32 byte abi encoding of the value.
+pub trait FromSegment: Sized {
+    fn from_segment(bytes: &[u8]) -> Option<Self>;
+}
+
+/// Reads the segment of `field` out of `encoded`.
+pub fn decode_segment<T: FromSegment>(
+    encoded: &[u8],
+    (offset, size): (usize, usize),
+    field: &str,
+) -> Result<T, QueryBuilderError> {
+    encoded
+        .get(offset..offset + size)
+        .and_then(T::from_segment)
+        .ok_or_else(|| QueryBuilderError::FailedToDecodeQueryField(field.to_string()))
+}
+
+fn word(bytes: &[u8]) -> Option<[u8; 32]> {
+    bytes.try_into().ok()
+}
+
+impl FromSegment for U256 {
+    fn from_segment(bytes: &[u8]) -> Option<Self> {
+        word(bytes).map(U256::from_be_bytes)
+    }
+}
+
+impl FromSegment for I256 {
+    fn from_segment(bytes: &[u8]) -> Option<Self> {
+        word(bytes).map(I256::from_be_bytes)
+    }
+}
+
+impl FromSegment for bool {
+    fn from_segment(bytes: &[u8]) -> Option<Self> {
+        match U256::from_segment(bytes)? {
+            value if value == U256::ZERO => Some(false),
+            value if value == U256::from(1) => Some(true),
+            _ => None,
+        }
+    }
+}
+
+impl FromSegment for Address {
+    fn from_segment(bytes: &[u8]) -> Option<Self> {
+        let word = word(bytes)?;
+        if word[..12].iter().any(|byte| *byte != 0) {
+            return None;
+        }
+        Some(Address::from_slice(&word[12..]))
+    }
+}
+
+/// Selectors, topics and other fixed size segments, taken as is.
+impl<const N: usize> FromSegment for FixedBytes<N> {
+    fn from_segment(bytes: &[u8]) -> Option<Self> {
+        bytes.try_into().ok().map(FixedBytes)
+    }
+}
+
+impl FromSegment for Bytes {
+    fn from_segment(bytes: &[u8]) -> Option<Self> {
+        Some(Bytes::copy_from_slice(bytes))
+    }
+}
+
+impl FromSegment for Vec<u8> {
+    fn from_segment(bytes: &[u8]) -> Option<Self> {
+        Some(bytes.to_vec())
+    }
+}
+
+impl FromSegment for String {
+    fn from_segment(bytes: &[u8]) -> Option<Self> {
+        String::from_utf8(bytes.to_vec()).ok()
+    }
+}
+
+macro_rules! impl_from_segment_for_uint {
+    ($($uint:ty),*) => {
+        $(
+            impl FromSegment for $uint {
+                fn from_segment(bytes: &[u8]) -> Option<Self> {
+                    U256::from_segment(bytes)?.try_into().ok()
+                }
+            }
+        )*
+    };
+}
+
+impl_from_segment_for_uint!(u8, u16, u32, u64, u128);
@@ -0,0 +1,214 @@
+//! ERC-4337 presets: user operations of `EntryPoint.handleOps` transactions and their
+//! `UserOperationEvent` logs, for the v0.6 and v0.7 entry points.
+
+use alloy::{
+    json_abi::{Event, Function},
+    primitives::{address, keccak256, Address, B256, U256},
+    sol,
+    sol_types::{JsonAbiExt, SolCall, SolValue},
+};
+
+use super::{
+    inline_abi::IntoFunctionAbi,
+    models::{FieldMetadata, QueryBuilderError},
+    query_builder_for_function::QueryBuilderForFunction,
+};
+
+sol! {
+    #![sol(abi)]
+    interface IEntryPointV06 {
+        struct UserOperation {
+            address sender;
+            uint256 nonce;
+            bytes initCode;
+            bytes callData;
+            uint256 callGasLimit;
+            uint256 verificationGasLimit;
+            uint256 preVerificationGas;
+            uint256 maxFeePerGas;
+            uint256 maxPriorityFeePerGas;
+            bytes paymasterAndData;
+            bytes signature;
+        }
+
+        function handleOps(UserOperation[] ops, address payable beneficiary);
+
+        event UserOperationEvent(bytes32 indexed userOpHash, address indexed sender, address indexed paymaster, uint256 nonce, bool success, uint256 actualGasCost, uint256 actualGasUsed);
+    }
+}
+
+sol! {
+    #![sol(abi)]
+    interface IEntryPointV07 {
+        struct PackedUserOperation {
+            address sender;
+            uint256 nonce;
+            bytes initCode;
+            bytes callData;
+            bytes32 accountGasLimits;
+            uint256 preVerificationGas;
+            bytes32 gasFees;
+            bytes paymasterAndData;
+            bytes signature;
+        }
+
+        function handleOps(PackedUserOperation[] ops, address payable beneficiary);
+
+        event UserOperationEvent(bytes32 indexed userOpHash, address indexed sender, address indexed paymaster, uint256 nonce, bool success, uint256 actualGasCost, uint256 actualGasUsed);
+    }
+}
+
+// `sender`, `nonce` and `callData` come first in both versions of the struct.
+const SENDER: usize = 0;
+const NONCE: usize = 1;
+const CALL_DATA: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryPointVersion {
+    V06,
+    V07,
+}
+
+impl EntryPointVersion {
+    /// Canonical deployment of the entry point.
+    pub fn address(self) -> Address {
+        match self {
+            EntryPointVersion::V06 => address!("5FF137D4b0FDCD49DcA30c7CF57E578a026d2789"),
+            EntryPointVersion::V07 => address!("0000000071727De22E5E9d8BAf0edAc6f37da032"),
+        }
+    }
+
+    pub fn handle_ops(self) -> Function {
+        match self {
+            EntryPointVersion::V06 => IEntryPointV06::handleOpsCall::abi(),
+            EntryPointVersion::V07 => IEntryPointV07::handleOpsCall::abi(),
+        }
+    }
+
+    pub fn user_operation_event(self) -> Event {
+        match self {
+            EntryPointVersion::V06 => IEntryPointV06::UserOperationEvent::abi(),
+            EntryPointVersion::V07 => IEntryPointV07::UserOperationEvent::abi(),
+        }
+    }
+
+    /// `userOpHash` of every operation of the `handleOps` `calldata`, as the entry point at
+    /// `entry_point` computes it on `chain_id`.
+    pub fn user_operation_hashes(
+        self,
+        calldata: &[u8],
+        entry_point: Address,
+        chain_id: u64,
+    ) -> Result<Vec<B256>, QueryBuilderError> {
+        let packed_operations: Vec<Vec<u8>> = match self {
+            EntryPointVersion::V06 => IEntryPointV06::handleOpsCall::abi_decode(calldata, true)
+                .map_err(|_| QueryBuilderError::FailedToComputeOffsetsForCalldata)?
+                .ops
+                .into_iter()
+                .map(|op| {
+                    (
+                        op.sender,
+                        op.nonce,
+                        keccak256(&op.initCode),
+                        keccak256(&op.callData),
+                        op.callGasLimit,
+                        op.verificationGasLimit,
+                        op.preVerificationGas,
+                        op.maxFeePerGas,
+                        op.maxPriorityFeePerGas,
+                        keccak256(&op.paymasterAndData),
+                    )
+                        .abi_encode()
+                })
+                .collect(),
+            EntryPointVersion::V07 => IEntryPointV07::handleOpsCall::abi_decode(calldata, true)
+                .map_err(|_| QueryBuilderError::FailedToComputeOffsetsForCalldata)?
+                .ops
+                .into_iter()
+                .map(|op| {
+                    (
+                        op.sender,
+                        op.nonce,
+                        keccak256(&op.initCode),
+                        keccak256(&op.callData),
+                        op.accountGasLimits,
+                        op.preVerificationGas,
+                        op.gasFees,
+                        keccak256(&op.paymasterAndData),
+                    )
+                        .abi_encode()
+                })
+                .collect(),
+        };
+
+        Ok(packed_operations
+            .into_iter()
+            .map(|packed| {
+                keccak256((keccak256(packed), entry_point, U256::from(chain_id)).abi_encode())
+            })
+            .collect())
+    }
+}
+
+/// Selects from a single user operation of a `handleOps` call, see
+/// [`super::query_builder::QueryBuilder::user_operation_builder`].
+pub struct UserOperationBuilder<'a> {
+    inner: &'a mut QueryBuilderForFunction,
+    user_operation: FieldMetadata,
+}
+
+impl<'a> UserOperationBuilder<'a> {
+    pub(crate) fn new(
+        inner: &'a mut QueryBuilderForFunction,
+        user_operation: FieldMetadata,
+    ) -> Self {
+        Self {
+            inner,
+            user_operation,
+        }
+    }
+
+    pub fn add_sender(&mut self) -> Result<&mut Self, QueryBuilderError> {
+        self.add_component(SENDER)
+    }
+
+    pub fn add_nonce(&mut self) -> Result<&mut Self, QueryBuilderError> {
+        self.add_component(NONCE)
+    }
+
+    /// The whole `callData` of the operation, without its length.
+    pub fn add_call_data(&mut self) -> Result<&mut Self, QueryBuilderError> {
+        self.add_component(CALL_DATA)
+    }
+
+    /// Opens a builder on the `callData` of the operation, the call made to the account,
+    /// e.g. `execute(address dest, uint256 value, bytes func)`. The call the account makes
+    /// in turn is reached with a nested builder on its `bytes` argument.
+    pub fn call_data_builder<T, C>(
+        &mut self,
+        function: T,
+        configurator: C,
+    ) -> Result<&mut Self, QueryBuilderError>
+    where
+        T: IntoFunctionAbi,
+        C: FnOnce(&mut QueryBuilderForFunction) -> Result<(), QueryBuilderError>,
+    {
+        let call_data = self.component(CALL_DATA)?;
+        self.inner
+            .nested_function_builder_at(call_data, function, configurator)?;
+        Ok(self)
+    }
+
+    fn component(&self, index: usize) -> Result<FieldMetadata, QueryBuilderError> {
+        match self.user_operation.children.get(index) {
+            Some(field) => Ok(field.clone()),
+            None => Err(QueryBuilderError::MissingDataInCalldataOffsets),
+        }
+    }
+
+    fn add_component(&mut self, index: usize) -> Result<&mut Self, QueryBuilderError> {
+        let field = self.component(index)?;
+        self.inner.add_field(&field)?;
+        Ok(self)
+    }
+}
@@ -0,0 +1,145 @@
+use std::marker::PhantomData;
+
+use alloy::{
+    json_abi::{Constructor, Event, Function, JsonAbi},
+    sol_types::JsonAbiExt,
+};
+
+use super::models::QueryBuilderError;
+
+/// Event definitions accepted by [`super::query_builder::QueryBuilder::event_builder_with_abi`]:
+/// an [`Event`], a human-readable signature such as
+/// `"event Transfer(address indexed from, address indexed to, uint256 value)"`, or a
+/// `sol!` event through [`sol_abi`]. `sol!` only generates the ABI of an event with
+/// `#[sol(abi)]` on it, or `#![sol(abi)]` at the top of the macro for interface items.
+pub trait IntoEventAbi {
+    fn into_event_abi(self) -> Result<Event, QueryBuilderError>;
+}
+
+/// Function definitions accepted by
+/// [`super::query_builder::QueryBuilder::function_builder_with_abi`], see [`IntoEventAbi`].
+pub trait IntoFunctionAbi {
+    fn into_function_abi(self) -> Result<Function, QueryBuilderError>;
+}
+
+/// Constructor definitions accepted by
+/// [`super::query_builder::QueryBuilder::constructor_builder_with_abi`]: a [`Constructor`],
+/// the constructor of a [`JsonAbi`], or a signature such as
+/// `"constructor(string name, uint256 supply)"`.
+pub trait IntoConstructorAbi {
+    fn into_constructor_abi(self) -> Result<Constructor, QueryBuilderError>;
+}
+
+/// Stands for the ABI of the `sol!` type `T`, built with [`sol_abi`].
+pub struct SolAbi<T>(PhantomData<fn() -> T>);
+
+/// e.g. `sol_abi::<IERC20::Transfer>()` or `sol_abi::<IERC20::transferCall>()`.
+pub fn sol_abi<T>() -> SolAbi<T> {
+    SolAbi(PhantomData)
+}
+
+/// A bare parameter list such as `"address recipient, uint256 amountIn"`, as a function
+/// named `params` to look arguments up in.
+pub(crate) fn params_abi(params: &str) -> Result<Function, QueryBuilderError> {
+    Function::parse(&format!("function params({params})")).map_err(|e| failed_to_parse(params, e))
+}
+
+fn failed_to_parse(signature: &str, error: impl ToString) -> QueryBuilderError {
+    QueryBuilderError::FailedToParseHumanReadableAbi {
+        signature: signature.to_string(),
+        error_message: error.to_string(),
+    }
+}
+
+impl IntoEventAbi for Event {
+    fn into_event_abi(self) -> Result<Event, QueryBuilderError> {
+        Ok(self)
+    }
+}
+
+impl IntoEventAbi for &Event {
+    fn into_event_abi(self) -> Result<Event, QueryBuilderError> {
+        Ok(self.clone())
+    }
+}
+
+impl IntoEventAbi for &str {
+    fn into_event_abi(self) -> Result<Event, QueryBuilderError> {
+        Event::parse(self).map_err(|e| failed_to_parse(self, e))
+    }
+}
+
+impl IntoEventAbi for String {
+    fn into_event_abi(self) -> Result<Event, QueryBuilderError> {
+        self.as_str().into_event_abi()
+    }
+}
+
+impl<T: JsonAbiExt<Abi = Event>> IntoEventAbi for SolAbi<T> {
+    fn into_event_abi(self) -> Result<Event, QueryBuilderError> {
+        Ok(T::abi())
+    }
+}
+
+impl IntoFunctionAbi for Function {
+    fn into_function_abi(self) -> Result<Function, QueryBuilderError> {
+        Ok(self)
+    }
+}
+
+impl IntoFunctionAbi for &Function {
+    fn into_function_abi(self) -> Result<Function, QueryBuilderError> {
+        Ok(self.clone())
+    }
+}
+
+impl IntoFunctionAbi for &str {
+    fn into_function_abi(self) -> Result<Function, QueryBuilderError> {
+        Function::parse(self).map_err(|e| failed_to_parse(self, e))
+    }
+}
+
+impl IntoFunctionAbi for String {
+    fn into_function_abi(self) -> Result<Function, QueryBuilderError> {
+        self.as_str().into_function_abi()
+    }
+}
+
+impl<T: JsonAbiExt<Abi = Function>> IntoFunctionAbi for SolAbi<T> {
+    fn into_function_abi(self) -> Result<Function, QueryBuilderError> {
+        Ok(T::abi())
+    }
+}
+
+impl IntoConstructorAbi for Constructor {
+    fn into_constructor_abi(self) -> Result<Constructor, QueryBuilderError> {
+        Ok(self)
+    }
+}
+
+impl IntoConstructorAbi for &Constructor {
+    fn into_constructor_abi(self) -> Result<Constructor, QueryBuilderError> {
+        Ok(self.clone())
+    }
+}
+
+impl IntoConstructorAbi for &JsonAbi {
+    fn into_constructor_abi(self) -> Result<Constructor, QueryBuilderError> {
+        match &self.constructor {
+            Some(constructor) => Ok(constructor.clone()),
+            None => Err(QueryBuilderError::NoConstructorInAbi),
+        }
+    }
+}
+
+impl IntoConstructorAbi for &str {
+    fn into_constructor_abi(self) -> Result<Constructor, QueryBuilderError> {
+        Constructor::parse(self).map_err(|e| failed_to_parse(self, e))
+    }
+}
+
+impl IntoConstructorAbi for String {
+    fn into_constructor_abi(self) -> Result<Constructor, QueryBuilderError> {
+        self.as_str().into_constructor_abi()
+    }
+}
@@ -0,0 +1,298 @@
+use std::{collections::BTreeMap, marker::PhantomData, ops::RangeInclusive};
+
+use alloy::{
+    dyn_abi::{DecodedEvent, DynSolType, DynSolValue, Specifier},
+    json_abi::Event,
+    primitives::{keccak256, Address, B256},
+    rpc::types::{Log, ValueOrArray},
+    sol_types::SolEvent,
+};
+use serde::{Deserialize, Serialize};
+
+use super::models::QueryBuilderError;
+
+/// A log matching an event: the log, its decoded event, its index in the receipt and the
+/// event it matched.
+pub type MatchedEvent = (Log, DecodedEvent, usize, Event);
+
+/// Picks logs among the ones matching an event. The event searches and builders of
+/// [`QueryBuilder`](super::query_builder::QueryBuilder) take a [`LogFilter`] or a
+/// `Fn(Log, DecodedEvent, usize) -> bool` closure.
+///
+/// Being generic over this trait, they can't infer the parameter types of a closure:
+/// closures that call methods on their arguments need them spelled out, e.g.
+/// `|log: Log, _, _| log.topics().len() == 3`.
+pub trait EventFilter {
+    /// Keeps the matches to select from, given in log order.
+    fn select(&self, matches: Vec<MatchedEvent>) -> Result<Vec<MatchedEvent>, QueryBuilderError>;
+}
+
+impl<F: Fn(Log, DecodedEvent, usize) -> bool> EventFilter for F {
+    fn select(&self, matches: Vec<MatchedEvent>) -> Result<Vec<MatchedEvent>, QueryBuilderError> {
+        Ok(matches
+            .into_iter()
+            .filter(|(log, decoded_event, log_index, _)| {
+                self(log.clone(), decoded_event.clone(), *log_index)
+            })
+            .collect())
+    }
+}
+
+/// [`EventFilter`] of the typed builders of the `sol!` event `T`: a [`LogFilter`] or a
+/// `Fn(Log, T, usize) -> bool` closure getting the decoded event. As with [`EventFilter`],
+/// closures reading the event need its type, e.g.
+/// `|_, transfer: IERC20::Transfer, _| transfer.value > cap`.
+pub trait TypedEventFilter<T> {
+    /// Keeps the matches to select from, given in log order.
+    fn select_typed(
+        &self,
+        matches: Vec<MatchedEvent>,
+    ) -> Result<Vec<MatchedEvent>, QueryBuilderError>;
+}
+
+impl<T: SolEvent, F: Fn(Log, T, usize) -> bool> TypedEventFilter<T> for F {
+    fn select_typed(
+        &self,
+        matches: Vec<MatchedEvent>,
+    ) -> Result<Vec<MatchedEvent>, QueryBuilderError> {
+        Ok(matches
+            .into_iter()
+            .filter(
+                |(log, _, log_index, _)| match T::decode_log_data(log.data(), true) {
+                    Ok(event) => self(log.clone(), event, *log_index),
+                    Err(_) => false,
+                },
+            )
+            .collect())
+    }
+}
+
+impl<T> TypedEventFilter<T> for LogFilter {
+    fn select_typed(
+        &self,
+        matches: Vec<MatchedEvent>,
+    ) -> Result<Vec<MatchedEvent>, QueryBuilderError> {
+        self.select(matches)
+    }
+}
+
+/// Runs a [`TypedEventFilter`] where an [`EventFilter`] is expected.
+pub(crate) fn typed_filter<T, F: TypedEventFilter<T>>(filter: F) -> impl EventFilter {
+    TypedFilter(filter, PhantomData::<fn() -> T>)
+}
+
+struct TypedFilter<T, F>(F, PhantomData<fn() -> T>);
+
+impl<T, F: TypedEventFilter<T>> EventFilter for TypedFilter<T, F> {
+    fn select(&self, matches: Vec<MatchedEvent>) -> Result<Vec<MatchedEvent>, QueryBuilderError> {
+        self.0.select_typed(matches)
+    }
+}
+
+/// Declarative [`EventFilter`] modelled on `eth_getLogs`. Every criterion is optional and
+/// a log has to pass all the ones set.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct LogFilter {
+    /// Emitter, or set of emitters.
+    pub address: Option<ValueOrArray<Address>>,
+    /// Accepted values per topic position, `None` accepting any topic.
+    pub topics: Vec<Option<ValueOrArray<B256>>>,
+    /// Decoded arguments by name, with values as `cast` parses them for the argument type.
+    /// Indexed `string`, `bytes`, arrays and structs are logged as a hash: give their plain
+    /// value, which is hashed to compare to the topic.
+    pub arguments: BTreeMap<String, String>,
+    /// Inclusive bounds on the index of the log in the receipt.
+    pub from_log_index: Option<usize>,
+    pub to_log_index: Option<usize>,
+    /// Only keeps the nth log, from 0, passing the other criteria.
+    pub nth: Option<usize>,
+}
+
+impl LogFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_address(mut self, address: impl Into<ValueOrArray<Address>>) -> Self {
+        self.address = Some(address.into());
+        self
+    }
+
+    pub fn with_topic(mut self, position: usize, topic: B256) -> Self {
+        if self.topics.len() <= position {
+            self.topics.resize(position + 1, None);
+        }
+        self.topics[position] = Some(ValueOrArray::Value(topic));
+        self
+    }
+
+    pub fn with_argument(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.arguments.insert(name.into(), value.into());
+        self
+    }
+
+    pub fn with_log_index_range(mut self, log_indexes: RangeInclusive<usize>) -> Self {
+        self.from_log_index = Some(*log_indexes.start());
+        self.to_log_index = Some(*log_indexes.end());
+        self
+    }
+
+    pub fn with_nth(mut self, nth: usize) -> Self {
+        self.nth = Some(nth);
+        self
+    }
+
+    /// Fails if an argument isn't one of the event or its value doesn't parse as its type.
+    pub fn matches(
+        &self,
+        log: &Log,
+        decoded_event: &DecodedEvent,
+        log_index: usize,
+        event: &Event,
+    ) -> Result<bool, QueryBuilderError> {
+        // all arguments are checked, in name order, so that a bad one fails whatever the
+        // other criteria and the first bad name is the one reported.
+        let mut arguments_match = true;
+        for (name, value) in &self.arguments {
+            arguments_match &= argument_equals(event, decoded_event, name, value).map_err(
+                |error| match error {
+                    ArgumentMatchError::UnknownArgument => {
+                        QueryBuilderError::UnknownEventArgument {
+                            event: event.name.clone(),
+                            argument: name.clone(),
+                        }
+                    }
+                    ArgumentMatchError::InvalidValue => {
+                        QueryBuilderError::InvalidArgumentFilterValue {
+                            argument: name.clone(),
+                            value: value.clone(),
+                        }
+                    }
+                },
+            )?;
+        }
+        if !arguments_match {
+            return Ok(false);
+        }
+
+        if let Some(address) = &self.address {
+            if !accepts(address, &log.address()) {
+                return Ok(false);
+            }
+        }
+
+        for (position, topic) in self.topics.iter().enumerate() {
+            if let Some(topic) = topic {
+                match log.topics().get(position) {
+                    Some(log_topic) if accepts(topic, log_topic) => {}
+                    _ => return Ok(false),
+                }
+            }
+        }
+
+        Ok(!(self.from_log_index.is_some_and(|from| log_index < from)
+            || self.to_log_index.is_some_and(|to| log_index > to)))
+    }
+}
+
+impl EventFilter for LogFilter {
+    fn select(&self, matches: Vec<MatchedEvent>) -> Result<Vec<MatchedEvent>, QueryBuilderError> {
+        let mut selected = Vec::new();
+        for matched_event in matches {
+            let (log, decoded_event, log_index, event) = &matched_event;
+            if self.matches(log, decoded_event, *log_index, event)? {
+                selected.push(matched_event);
+            }
+        }
+
+        match self.nth {
+            Some(nth) => Ok(selected.into_iter().skip(nth).take(1).collect()),
+            None => Ok(selected),
+        }
+    }
+}
+
+/// An empty array accepts anything, like in `eth_getLogs`.
+fn accepts<T: PartialEq>(accepted: &ValueOrArray<T>, value: &T) -> bool {
+    match accepted {
+        ValueOrArray::Value(accepted) => accepted == value,
+        ValueOrArray::Array(accepted) => accepted.is_empty() || accepted.contains(value),
+    }
+}
+
+pub(crate) enum ArgumentMatchError {
+    UnknownArgument,
+    InvalidValue,
+}
+
+/// Whether the argument `name` of the event equals `value`, written the way `cast` parses
+/// it for the argument type. Indexed arguments that don't fit a topic are only logged as
+/// the hash of their value, so `value` is hashed the same way to compare.
+pub(crate) fn argument_equals(
+    event: &Event,
+    decoded_event: &DecodedEvent,
+    name: &str,
+    value: &str,
+) -> Result<bool, ArgumentMatchError> {
+    let argument_index = match event.inputs.iter().position(|input| input.name == name) {
+        Some(argument_index) => argument_index,
+        None => return Err(ArgumentMatchError::UnknownArgument),
+    };
+    let input = &event.inputs[argument_index];
+    let preceding_inputs = &event.inputs[..argument_index];
+    let decoded_value = if input.indexed {
+        let topic_index = preceding_inputs.iter().filter(|i| i.indexed).count();
+        decoded_event.indexed.get(topic_index)
+    } else {
+        let data_index = preceding_inputs.iter().filter(|i| !i.indexed).count();
+        decoded_event.body.get(data_index)
+    };
+
+    let sol_type = input
+        .resolve()
+        .map_err(|_| ArgumentMatchError::UnknownArgument)?;
+    let expected = sol_type
+        .coerce_str(value)
+        .map_err(|_| ArgumentMatchError::InvalidValue)?;
+
+    Ok(match decoded_value {
+        Some(DynSolValue::FixedBytes(topic, 32)) if input.indexed && !fits_in_topic(&sol_type) => {
+            let mut preimage = Vec::new();
+            encode_topic_preimage(&expected, false, &mut preimage);
+            *topic == keccak256(preimage)
+        }
+        Some(decoded_value) => *decoded_value == expected,
+        None => false,
+    })
+}
+
+fn fits_in_topic(sol_type: &DynSolType) -> bool {
+    matches!(
+        sol_type,
+        DynSolType::Address
+            | DynSolType::Function
+            | DynSolType::Bool
+            | DynSolType::FixedBytes(_)
+            | DynSolType::Int(_)
+            | DynSolType::Uint(_)
+    )
+}
+
+/// Hashed encoding of indexed arguments: `bytes` and `string` are written as is, words
+/// unchanged, and arrays and tuples as their elements in a row, padding nested `bytes` and
+/// `string` to whole words.
+fn encode_topic_preimage(value: &DynSolValue, nested: bool, preimage: &mut Vec<u8>) {
+    if let Some(word) = value.as_word() {
+        preimage.extend_from_slice(word.as_slice());
+    } else if let Some(values) = value.as_fixed_seq().or_else(|| value.as_array()) {
+        for value in values {
+            encode_topic_preimage(value, true, preimage);
+        }
+    } else {
+        value.abi_encode_packed_to(preimage);
+        if nested {
+            preimage.resize(preimage.len().next_multiple_of(32), 0);
+        }
+    }
+}
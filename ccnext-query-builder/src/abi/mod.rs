@@ -1,6 +1,17 @@
+pub mod ccnext_query;
+pub mod erc4337;
 pub mod field_mapping;
+pub mod inline_abi;
+pub mod log_filter;
 pub mod models;
+pub mod providers;
 pub mod query_builder;
+pub mod query_builder_for_constructor;
 pub mod query_builder_for_event;
 pub mod query_builder_for_function;
+pub mod query_spec;
+pub mod registry;
+pub mod rpc;
+pub mod signatures;
+pub mod typed;
 pub mod utils;
@@ -1,5 +1,12 @@
-use alloy::{dyn_abi::DynSolType, rpc::types::Log};
+use alloy::{
+    dyn_abi::DynSolType,
+    primitives::{Address, B256},
+    rpc::types::Log,
+};
 use alloy_json_abi::{Event, Function};
+use serde::{Deserialize, Serialize};
+
+use super::query_spec::QuerySpecError;
 
 #[derive(Debug, Clone)]
 pub struct FieldMetadata {
@@ -11,7 +18,108 @@ pub struct FieldMetadata {
     pub children: Vec<FieldMetadata>,
 }
 
-#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+/// Where an originally selected `(offset, size)` ended up after normalization.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct SegmentPosition {
+    /// Index into [`NormalizedSegments::segments`].
+    pub segment_index: usize,
+    /// Byte offset of the original selection inside that segment.
+    pub offset_in_segment: usize,
+}
+
+/// Sorted, deduplicated and coalesced view over the selected offsets.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct NormalizedSegments {
+    /// Minimal set of non-overlapping, non-adjacent `(offset, size)` ranges.
+    pub segments: Vec<(usize, usize)>,
+    /// One entry per original selection, in the order they were selected.
+    pub selections: Vec<SegmentPosition>,
+}
+
+/// What an [`super::query_builder::AbiProvider`] is asked to resolve.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct AbiRequestContext {
+    /// `None` for pre EIP-155 transactions unless set on the builder.
+    pub chain_id: Option<u64>,
+    pub address: Address,
+    /// Block of the transaction being queried, `None` when unknown.
+    pub block_number: Option<u64>,
+}
+
+impl AbiRequestContext {
+    pub fn new(address: Address) -> Self {
+        Self {
+            chain_id: None,
+            address,
+            block_number: None,
+        }
+    }
+
+    pub fn with_chain_id(mut self, chain_id: u64) -> Self {
+        self.chain_id = Some(chain_id);
+        self
+    }
+
+    pub fn with_block_number(mut self, block_number: u64) -> Self {
+        self.block_number = Some(block_number);
+        self
+    }
+}
+
+/// A `bytes` argument carrying the calldata of an inner call, such as the `data` of a Safe
+/// `execTransaction`, or an element of a `bytes[]` one, such as `multicall(bytes[] data)`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct NestedCalldata {
+    argument: String,
+    element: Option<usize>,
+    target: NestedCallTarget,
+}
+
+impl NestedCalldata {
+    pub fn new(argument: impl Into<String>) -> Self {
+        Self {
+            argument: argument.into(),
+            element: None,
+            target: NestedCallTarget::default(),
+        }
+    }
+
+    pub fn with_element(mut self, element: usize) -> Self {
+        self.element = Some(element);
+        self
+    }
+
+    pub fn with_target(mut self, target: NestedCallTarget) -> Self {
+        self.target = target;
+        self
+    }
+
+    pub fn argument(&self) -> &str {
+        &self.argument
+    }
+
+    /// Element of a `bytes[]` argument, `None` for a `bytes` one.
+    pub fn element(&self) -> Option<usize> {
+        self.element
+    }
+
+    /// Contract whose ABI describes the inner call.
+    pub fn target(&self) -> &NestedCallTarget {
+        &self.target
+    }
+}
+
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub enum NestedCallTarget {
+    /// The contract called by the transaction, e.g. for `multicall`.
+    #[default]
+    Transaction,
+    /// An `address` argument of the outer function, e.g. `to` of `execTransaction`.
+    Argument(String),
+    Address(Address),
+}
+
+#[derive(Debug, Clone, Hash, Eq, PartialEq, Serialize, Deserialize)]
 pub enum QueryableFields {
     Type,
     TxChainId,
@@ -39,7 +147,7 @@ pub enum QueryableFields {
     TxSignedAuthorizations,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum QueryBuilderError {
     FailedToAbiEncode,
     FailedToComputeOffsets,
@@ -75,4 +183,113 @@ pub enum QueryBuilderError {
     FailedToResolveSolTypesOfMatchedEvent(Event),
     FailedToComputeOffsetsForCalldata,
     MissingDataInCalldataOffsets,
+    InvalidQuerySpec(QuerySpecError),
+    ProviderRequestFailed(String),
+    TransactionNotFound(B256),
+    TransactionPending(B256),
+    ReceiptNotFound(B256),
+    ReceiptTransactionHashMismatch {
+        expected: B256,
+        actual: B256,
+    },
+    ReceiptBlockHashMismatch {
+        tx_hash: B256,
+        tx_block_hash: B256,
+        receipt_block_hash: Option<B256>,
+    },
+    FailedToReadArtifacts {
+        path: String,
+        error_message: String,
+    },
+    ArtifactNotFound(String),
+    AmbiguousArtifactName(String),
+    /// More than one address failed while resolving ABIs concurrently, sorted by address.
+    FailedToRetrieveAbis(Vec<(Address, QueryBuilderError)>),
+    FailedToReadSignatureDatabase {
+        path: String,
+        error_message: String,
+    },
+    FailedToParseSignatureDatabase {
+        line: usize,
+        error_message: String,
+    },
+    /// Several signature database entries fit the same selector or topic0.
+    AmbiguousSignatureMatch {
+        selector: String,
+        candidates: Vec<String>,
+    },
+    FailedToParseHumanReadableAbi {
+        signature: String,
+        error_message: String,
+    },
+    /// The calldata selector isn't the one of the function the query was built for.
+    FunctionDoesNotMatchCalldata {
+        function: String,
+        calldata_selector: String,
+    },
+    /// The segment of a `CcnextQuery` field is missing or doesn't hold a value of its type.
+    FailedToDecodeQueryField(String),
+    /// Anonymous events can only be matched among the logs of a given contract.
+    AnonymousEventNeedsAddress(String),
+    AnonymousEventHasNoSignature(String),
+    /// Nested function builders need a `bytes` argument, or `bytes[]` with an element.
+    ArgumentIsNotNestedCalldata(String),
+    ArgumentIsNotAnAddress(String),
+    /// Nested parameters carry no selector to select.
+    NestedParamsHaveNoSelector,
+    /// `handleOps` has no user operation at that index.
+    UserOperationNotFound(usize),
+    /// `userOpHash` commits to the chain id, which pre EIP-155 transactions don't carry.
+    MissingChainId,
+    /// Constructor arguments only exist in transactions without `to`.
+    NotAContractCreation,
+    NoConstructorInAbi,
+    CannotFindArgumentInConstructor(String),
+    FailedToResolveSolTypesOfConstructor,
+    /// The calldata doesn't start with the creation bytecode the arguments are expected after.
+    CreationBytecodeDoesNotMatch,
+    FailedToParseArtifact(String),
+    /// Logs selected by index have no event to look arguments up in.
+    RawLogHasNoEvent,
+    TopicIndexOutOfBounds {
+        topic_index: usize,
+        topic_count: usize,
+    },
+    /// A raw byte range doesn't fit in the field it was selected from.
+    RangeOutOfBounds {
+        start: usize,
+        size: usize,
+        field_size: usize,
+    },
+    /// A [`super::log_filter::LogFilter`] or typed event builder names an argument the event
+    /// doesn't have.
+    UnknownEventArgument {
+        event: String,
+        argument: String,
+    },
+    /// A [`super::log_filter::LogFilter`] argument value doesn't parse as the argument type.
+    InvalidArgumentFilterValue {
+        argument: String,
+        value: String,
+    },
+}
+
+/// How event searches treat logs they can't decode, typically from unverified contracts
+/// the transaction happens to touch.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EventSearchMode {
+    /// Any log without ABI or that fails to decode fails the search.
+    #[default]
+    Strict,
+    /// Such logs are skipped and recorded as [`QueryWarning`]s.
+    Tolerant,
+}
+
+#[derive(Debug, Clone)]
+pub enum QueryWarning {
+    SkippedLog {
+        log_index: usize,
+        address: Address,
+        error: QueryBuilderError,
+    },
 }
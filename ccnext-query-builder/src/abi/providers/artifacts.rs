@@ -0,0 +1,458 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use alloy::{
+    eips::BlockId,
+    json_abi::JsonAbi,
+    primitives::{Address, Bytes},
+    providers::{DynProvider, Provider},
+};
+use async_trait::async_trait;
+use serde_json::Value;
+
+use crate::abi::{
+    models::{AbiRequestContext, QueryBuilderError},
+    query_builder::AbiProvider,
+};
+
+/// A compiled contract as found in a Foundry (`out/`) or Hardhat (`artifacts/`) directory.
+#[derive(Debug, Clone)]
+pub struct ContractArtifact {
+    pub name: String,
+    pub path: PathBuf,
+    pub abi: JsonAbi,
+    /// Creation (init) code, `None` when it still contains unlinked library placeholders.
+    pub bytecode: Option<Bytes>,
+    /// Runtime code, `None` when it still contains unlinked library placeholders.
+    pub deployed_bytecode: Option<Bytes>,
+    /// `(start, length)` of the immutables in the runtime code, filled in at deployment.
+    /// Only Foundry artifacts carry them, Hardhat keeps them in its build-info files.
+    pub immutable_references: Vec<(usize, usize)>,
+}
+
+/// Every contract artifact found under a directory.
+#[derive(Debug, Clone, Default)]
+pub struct ArtifactIndex {
+    artifacts: Vec<ContractArtifact>,
+}
+
+impl ArtifactIndex {
+    /// Recursively indexes every `*.json` file under `dir` that has a top-level `abi`.
+    /// Hardhat debug files and build-info files are skipped since they don't.
+    pub fn from_dir(dir: impl AsRef<Path>) -> Result<Self, QueryBuilderError> {
+        let mut artifacts = Vec::new();
+        index_dir(dir.as_ref(), &mut artifacts)?;
+        artifacts.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(Self { artifacts })
+    }
+
+    pub fn artifacts(&self) -> &[ContractArtifact] {
+        &self.artifacts
+    }
+
+    /// Looks an artifact up by contract name, or by `path:Name` when the name alone is
+    /// ambiguous (e.g. `out/Token.sol/Token.json:Token`).
+    pub fn get(&self, name: &str) -> Result<&ContractArtifact, QueryBuilderError> {
+        Ok(&self.artifacts[self.position(name)?])
+    }
+
+    fn position(&self, name: &str) -> Result<usize, QueryBuilderError> {
+        let (path, name) = match name.rsplit_once(':') {
+            Some((path, name)) => (Some(path), name),
+            None => (None, name),
+        };
+
+        let mut candidates = self.artifacts.iter().enumerate().filter(|(_, artifact)| {
+            artifact.name == name && path.is_none_or(|path| artifact.path.ends_with(path))
+        });
+
+        match (candidates.next(), candidates.next()) {
+            (Some((position, _)), None) => Ok(position),
+            (Some(_), Some(_)) => Err(QueryBuilderError::AmbiguousArtifactName(name.into())),
+            (None, _) => Err(QueryBuilderError::ArtifactNotFound(name.into())),
+        }
+    }
+
+    /// Finds the artifact whose runtime code matches `code`, ignoring the trailing
+    /// CBOR metadata, which changes with every unrelated source edit, and the immutables,
+    /// which the artifact only holds as zeroes.
+    pub fn find_by_deployed_bytecode(&self, code: &[u8]) -> Option<&ContractArtifact> {
+        if strip_metadata(code).is_empty() {
+            return None;
+        }
+
+        self.artifacts.iter().find(|artifact| {
+            let deployed = match &artifact.deployed_bytecode {
+                Some(deployed) => deployed,
+                None => return false,
+            };
+            match (
+                mask_immutables(code, &artifact.immutable_references),
+                mask_immutables(deployed, &artifact.immutable_references),
+            ) {
+                (Some(code), Some(deployed)) => strip_metadata(&code) == strip_metadata(&deployed),
+                _ => false,
+            }
+        })
+    }
+}
+
+fn index_dir(dir: &Path, artifacts: &mut Vec<ContractArtifact>) -> Result<(), QueryBuilderError> {
+    let read_failed =
+        |path: &Path, error: std::io::Error| QueryBuilderError::FailedToReadArtifacts {
+            path: path.display().to_string(),
+            error_message: error.to_string(),
+        };
+
+    for entry in fs::read_dir(dir).map_err(|e| read_failed(dir, e))? {
+        let path = entry.map_err(|e| read_failed(dir, e))?.path();
+        if path.is_dir() {
+            index_dir(&path, artifacts)?;
+        } else if path
+            .extension()
+            .is_some_and(|extension| extension == "json")
+        {
+            let content = fs::read_to_string(&path).map_err(|e| read_failed(&path, e))?;
+            if let Some(artifact) = parse_artifact(&path, &content) {
+                artifacts.push(artifact);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn parse_artifact(path: &Path, content: &str) -> Option<ContractArtifact> {
+    let json: Value = serde_json::from_str(content).ok()?;
+    let abi: JsonAbi = serde_json::from_value(json.get("abi")?.clone()).ok()?;
+
+    // Hardhat names the contract, Foundry names the file after it.
+    let name = match json.get("contractName").and_then(Value::as_str) {
+        Some(name) => name.to_string(),
+        None => path.file_stem()?.to_str()?.to_string(),
+    };
+
+    Some(ContractArtifact {
+        name,
+        path: path.to_path_buf(),
+        abi,
+        bytecode: parse_bytecode(json.get("bytecode")),
+        deployed_bytecode: parse_bytecode(json.get("deployedBytecode")),
+        immutable_references: parse_immutable_references(json.get("deployedBytecode")),
+    })
+}
+
+/// Hardhat stores bytecode as a hex string, Foundry as `{ "object": "0x..." }`.
+fn parse_bytecode(value: Option<&Value>) -> Option<Bytes> {
+    let hex = match value? {
+        Value::String(hex) => hex.as_str(),
+        other => other.get("object")?.as_str()?,
+    };
+    Bytes::from_str(hex).ok().filter(|bytes| !bytes.is_empty())
+}
+
+/// Foundry stores `{ "<ast id>": [{ "start": n, "length": 32 }] }` next to the runtime code.
+fn parse_immutable_references(deployed_bytecode: Option<&Value>) -> Vec<(usize, usize)> {
+    let references = match deployed_bytecode
+        .and_then(|deployed| deployed.get("immutableReferences"))
+        .and_then(Value::as_object)
+    {
+        Some(references) => references,
+        None => return vec![],
+    };
+
+    let mut ranges: Vec<(usize, usize)> = references
+        .values()
+        .filter_map(Value::as_array)
+        .flatten()
+        .filter_map(|reference| {
+            let start = reference.get("start")?.as_u64()?;
+            let length = reference.get("length")?.as_u64()?;
+            Some((start as usize, length as usize))
+        })
+        .collect();
+    ranges.sort_unstable();
+    ranges
+}
+
+/// `code` with the immutable ranges zeroed, `None` when they don't fit in it.
+fn mask_immutables(code: &[u8], immutable_references: &[(usize, usize)]) -> Option<Vec<u8>> {
+    let mut masked = code.to_vec();
+    for (start, length) in immutable_references {
+        masked.get_mut(*start..start.checked_add(*length)?)?.fill(0);
+    }
+    Some(masked)
+}
+
+/// Solidity appends `<cbor metadata><2 byte big endian length>` to the runtime code.
+pub(crate) fn strip_metadata(code: &[u8]) -> &[u8] {
+    if code.len() < 2 {
+        return code;
+    }
+
+    let metadata_length = u16::from_be_bytes([code[code.len() - 2], code[code.len() - 1]]) as usize;
+    let metadata_start = match code.len().checked_sub(metadata_length + 2) {
+        Some(start) => start,
+        None => return code,
+    };
+
+    // the metadata is always a CBOR map.
+    match code.get(metadata_start) {
+        Some(0xa0..=0xbf) => &code[..metadata_start],
+        _ => code,
+    }
+}
+
+/// [`AbiProvider`] backed by local compile artifacts, so CI and local development don't
+/// need an explorer. Addresses are resolved through an explicit map first, then by
+/// matching their deployed code against the artifacts.
+pub struct ArtifactAbiProvider {
+    index: ArtifactIndex,
+    addresses: HashMap<Address, usize>,
+    provider: Option<DynProvider>,
+}
+
+impl ArtifactAbiProvider {
+    pub fn new(index: ArtifactIndex) -> Self {
+        Self {
+            index,
+            addresses: HashMap::new(),
+            provider: None,
+        }
+    }
+
+    /// Maps an address to an artifact, see [`ArtifactIndex::get`] for the name format.
+    pub fn with_address(
+        mut self,
+        address: Address,
+        contract_name: &str,
+    ) -> Result<Self, QueryBuilderError> {
+        let position = self.index.position(contract_name)?;
+        self.addresses.insert(address, position);
+        Ok(self)
+    }
+
+    /// Enables matching unmapped addresses by the code `provider` returns for them.
+    pub fn with_bytecode_matching<P: Provider + 'static>(mut self, provider: P) -> Self {
+        self.provider = Some(provider.erased());
+        self
+    }
+
+    pub fn index(&self) -> &ArtifactIndex {
+        &self.index
+    }
+
+    async fn find_artifact(
+        &self,
+        context: &AbiRequestContext,
+    ) -> Result<Option<&ContractArtifact>, QueryBuilderError> {
+        if let Some(position) = self.addresses.get(&context.address) {
+            return Ok(self.index.artifacts.get(*position));
+        }
+
+        let provider = match &self.provider {
+            Some(p) => p,
+            None => return Ok(None),
+        };
+
+        // the code the transaction actually ran against, in case the contract changed since.
+        let block_id = match context.block_number {
+            Some(block_number) => BlockId::number(block_number),
+            None => BlockId::latest(),
+        };
+        let code = provider
+            .get_code_at(context.address)
+            .block_id(block_id)
+            .await
+            .map_err(|e| QueryBuilderError::ContractAbiRetrievalFailed {
+                contract_addr: context.address.to_string(),
+                error_message: e.to_string(),
+            })?;
+        Ok(self.index.find_by_deployed_bytecode(&code))
+    }
+}
+
+#[async_trait]
+impl AbiProvider for ArtifactAbiProvider {
+    async fn get_abi(&self, context: &AbiRequestContext) -> Result<String, QueryBuilderError> {
+        match self.find_artifact(context).await? {
+            Some(artifact) => serde_json::to_string(&artifact.abi).map_err(|e| {
+                QueryBuilderError::ContractAbiRetrievalFailed {
+                    contract_addr: context.address.to_string(),
+                    error_message: e.to_string(),
+                }
+            }),
+            None => Err(QueryBuilderError::NoAbiFoundForContract(
+                context.address.to_string(),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use serde_json::json;
+
+    use crate::mock_transport::MockTransport;
+
+    const TOKEN_RUNTIME: &str = "0x6080604052348015600f57600080fd5b50";
+    const VAULT_RUNTIME: &str = "0x60806040526004361060265760003560e01c";
+    // `{"ipfs": <34 bytes>, "solc": <3 bytes>}` style trailers, only the hash differs.
+    const METADATA_A: &str = "a264697066735822aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa64736f6c63430008180033";
+    const METADATA_B: &str = "a264697066735822bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb64736f6c63430008180033";
+
+    const TOKEN: &str = "0xAc1D3D7A8878E655cBb063D58E453540641f4117";
+    const VAULT: &str = "0x73f7b1184B5cD361cC0f7654998953E2a251dd58";
+
+    fn context(address: &str) -> AbiRequestContext {
+        AbiRequestContext::new(Address::from_str(address).unwrap())
+    }
+
+    fn write_artifacts() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+
+        // Foundry layout.
+        let foundry = dir.path().join("out/Token.sol");
+        fs::create_dir_all(&foundry).unwrap();
+        let token = json!({
+            "abi": [{ "type": "function", "name": "mint", "inputs": [{ "name": "to", "type": "address" }], "outputs": [], "stateMutability": "nonpayable" }],
+            "bytecode": { "object": "0x6080604052", "linkReferences": {} },
+            "deployedBytecode": { "object": format!("{TOKEN_RUNTIME}{METADATA_A}"), "linkReferences": {} },
+        });
+        fs::write(foundry.join("Token.json"), token.to_string()).unwrap();
+
+        // Hardhat layout, including the debug file next to the artifact.
+        let hardhat = dir.path().join("artifacts/contracts/Vault.sol");
+        fs::create_dir_all(&hardhat).unwrap();
+        let vault = json!({
+            "_format": "hh-sol-artifact-1",
+            "contractName": "Vault",
+            "sourceName": "contracts/Vault.sol",
+            "abi": [{ "type": "event", "name": "Deposited", "anonymous": false, "inputs": [{ "name": "sender", "type": "address", "indexed": true }] }],
+            "bytecode": "0x6080604052",
+            "deployedBytecode": format!("{VAULT_RUNTIME}{METADATA_A}"),
+        });
+        fs::write(hardhat.join("Vault.json"), vault.to_string()).unwrap();
+        fs::write(
+            hardhat.join("Vault.dbg.json"),
+            r#"{"_format":"hh-sol-dbg-1","buildInfo":"../../build-info/1.json"}"#,
+        )
+        .unwrap();
+
+        dir
+    }
+
+    #[test]
+    fn indexes_foundry_and_hardhat_artifacts() {
+        let dir = write_artifacts();
+        let index = ArtifactIndex::from_dir(dir.path()).unwrap();
+
+        let names: Vec<&str> = index.artifacts().iter().map(|a| a.name.as_str()).collect();
+        assert_eq!(names, vec!["Vault", "Token"]);
+        assert!(index.get("Token").unwrap().abi.function("mint").is_some());
+        assert!(index.get("Vault").unwrap().abi.event("Deposited").is_some());
+        assert!(matches!(
+            index.get("Missing"),
+            Err(QueryBuilderError::ArtifactNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn strip_metadata_ignores_code_without_trailer() {
+        let code = Bytes::from_str(TOKEN_RUNTIME).unwrap();
+        assert_eq!(strip_metadata(&code), &code[..]);
+
+        let with_metadata = Bytes::from_str(&format!("{TOKEN_RUNTIME}{METADATA_B}")).unwrap();
+        assert_eq!(strip_metadata(&with_metadata), &code[..]);
+    }
+
+    #[tokio::test]
+    async fn resolves_addresses_from_explicit_map() {
+        let dir = write_artifacts();
+        let provider = ArtifactAbiProvider::new(ArtifactIndex::from_dir(dir.path()).unwrap())
+            .with_address(Address::from_str(VAULT).unwrap(), "Vault")
+            .unwrap();
+
+        let abi =
+            JsonAbi::from_json_str(&provider.get_abi(&context(VAULT)).await.unwrap()).unwrap();
+        assert!(abi.event("Deposited").is_some());
+
+        // no map entry and no bytecode matching.
+        assert!(matches!(
+            provider.get_abi(&context(TOKEN)).await,
+            Err(QueryBuilderError::NoAbiFoundForContract(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn resolves_addresses_by_deployed_bytecode() {
+        let dir = write_artifacts();
+        let transport = MockTransport::default();
+        // deployed from a build whose metadata hash differs from the local one.
+        transport
+            .push_response("eth_getCode", json!(format!("{TOKEN_RUNTIME}{METADATA_B}")))
+            .push_response("eth_getCode", json!("0x6001600155"));
+
+        let provider = ArtifactAbiProvider::new(ArtifactIndex::from_dir(dir.path()).unwrap())
+            .with_bytecode_matching(transport.provider());
+
+        let at_block = context(TOKEN).with_block_number(0x6c1a2b);
+        let abi = JsonAbi::from_json_str(&provider.get_abi(&at_block).await.unwrap()).unwrap();
+        assert!(abi.function("mint").is_some());
+
+        assert!(matches!(
+            provider.get_abi(&context(VAULT)).await,
+            Err(QueryBuilderError::NoAbiFoundForContract(_))
+        ));
+
+        let requests = transport.requests();
+        assert_eq!(requests[0].1, json!([TOKEN.to_lowercase(), "0x6c1a2b"]));
+        assert_eq!(requests[1].1[1], json!("latest"));
+    }
+
+    #[tokio::test]
+    async fn immutables_are_ignored_when_matching_deployed_bytecode() {
+        // `PUSH32 <immutable> PUSH1 0 MSTORE`, the artifact only has zeroes in place of it.
+        let runtime = |immutable: &str| format!("0x7f{immutable}600052{METADATA_A}");
+        let dir = tempfile::tempdir().unwrap();
+        let permit = json!({
+            "abi": [{ "type": "function", "name": "DOMAIN_SEPARATOR", "inputs": [], "outputs": [{ "name": "", "type": "bytes32" }], "stateMutability": "view" }],
+            "bytecode": { "object": "0x6080604052", "linkReferences": {} },
+            "deployedBytecode": {
+                "object": runtime(&"00".repeat(32)),
+                "linkReferences": {},
+                "immutableReferences": { "1021": [{ "start": 1, "length": 32 }] },
+            },
+        });
+        fs::write(dir.path().join("Permit.json"), permit.to_string()).unwrap();
+        let index = ArtifactIndex::from_dir(dir.path()).unwrap();
+        assert_eq!(index.get("Permit").unwrap().immutable_references, [(1, 32)]);
+
+        let deployed = Bytes::from_str(&runtime(&"ab".repeat(32))).unwrap();
+        assert_eq!(
+            index.find_by_deployed_bytecode(&deployed).unwrap().name,
+            "Permit"
+        );
+        // the code around the immutable still has to match.
+        let other =
+            Bytes::from_str(&runtime(&"ab".repeat(32)).replace("600052", "600152")).unwrap();
+        assert!(index.find_by_deployed_bytecode(&other).is_none());
+        assert!(index.find_by_deployed_bytecode(&deployed[..20]).is_none());
+    }
+
+    #[test]
+    fn mapping_an_address_to_a_missing_artifact_fails() {
+        let dir = write_artifacts();
+        let provider = ArtifactAbiProvider::new(ArtifactIndex::from_dir(dir.path()).unwrap());
+        assert!(matches!(
+            provider.with_address(Address::from_str(VAULT).unwrap(), "Missing"),
+            Err(QueryBuilderError::ArtifactNotFound(_))
+        ));
+    }
+}
@@ -0,0 +1,353 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use alloy::json_abi::JsonAbi;
+use async_trait::async_trait;
+use tokio::fs;
+
+use crate::abi::{
+    models::{AbiRequestContext, QueryBuilderError},
+    query_builder::AbiProvider,
+};
+
+/// Persists ABIs returned by the wrapped provider under
+/// `<dir>/<chain id>/<address>.json`, so they survive across builders and processes.
+/// Requests without a chain id are passed through uncached, as the same address may
+/// hold unrelated contracts on different chains.
+///
+/// Entries are written to a temporary file and renamed into place, so concurrent
+/// processes never read a partial entry, and only answers that parse as an ABI are kept.
+///
+/// "Not found" answers are only cached when a negative TTL is set, as
+/// `<address>.not_found` files holding the unix time of the lookup. Other failures are
+/// never cached.
+///
+/// The ABI of an address is cached once for all blocks, which is right for explorers and
+/// artifacts but not for a provider answering per block, such as a
+/// [`super::proxy::ProxyResolvingAbiProvider`]: either wrap the explorer underneath it, or
+/// enable [`Self::with_per_block_cache`].
+pub struct DiskCacheAbiProvider<P> {
+    inner: P,
+    dir: PathBuf,
+    negative_ttl: Option<Duration>,
+    per_block_cache: bool,
+}
+
+impl<P> DiskCacheAbiProvider<P> {
+    pub fn new(inner: P, dir: impl Into<PathBuf>) -> Self {
+        Self {
+            inner,
+            dir: dir.into(),
+            negative_ttl: None,
+            per_block_cache: false,
+        }
+    }
+
+    /// Remembers that a contract has no ABI for `ttl`, e.g. so an unverified contract
+    /// isn't looked up again on every query.
+    pub fn with_negative_ttl(mut self, ttl: Duration) -> Self {
+        self.negative_ttl = Some(ttl);
+        self
+    }
+
+    /// Keys entries on the block too, as `<dir>/<chain id>/<address>/<block>.json`, for
+    /// inner providers whose answer depends on the block, like an upgradeable proxy.
+    /// Requests without a block number are then passed through uncached.
+    pub fn with_per_block_cache(mut self, per_block_cache: bool) -> Self {
+        self.per_block_cache = per_block_cache;
+        self
+    }
+
+    /// `None` when the request can't be cached.
+    fn entry_path(&self, context: &AbiRequestContext, extension: &str) -> Option<PathBuf> {
+        let chain_dir = self.dir.join(context.chain_id?.to_string());
+        let address = format!("{:#x}", context.address);
+        if self.per_block_cache {
+            let block_number = context.block_number?;
+            Some(
+                chain_dir
+                    .join(address)
+                    .join(format!("{block_number}.{extension}")),
+            )
+        } else {
+            Some(chain_dir.join(format!("{address}.{extension}")))
+        }
+    }
+
+    async fn is_known_missing(&self, not_found_path: &Path) -> bool {
+        let ttl = match self.negative_ttl {
+            Some(ttl) => ttl,
+            None => return false,
+        };
+
+        let looked_up_at = fs::read_to_string(not_found_path)
+            .await
+            .ok()
+            .and_then(|content| content.trim().parse::<u64>().ok());
+
+        match looked_up_at {
+            Some(looked_up_at) => unix_time().saturating_sub(looked_up_at) < ttl.as_secs(),
+            None => false,
+        }
+    }
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+fn is_abi(content: &str) -> bool {
+    JsonAbi::from_json_str(content).is_ok()
+}
+
+/// The cache is best effort: a failed write only costs another lookup next time.
+async fn write_entry(path: &Path, content: &str) {
+    static WRITES: AtomicUsize = AtomicUsize::new(0);
+
+    let (parent, file_name) = match (path.parent(), path.file_name()) {
+        (Some(parent), Some(file_name)) => (parent, file_name.to_string_lossy()),
+        _ => return,
+    };
+    if fs::create_dir_all(parent).await.is_err() {
+        return;
+    }
+
+    // unique per process and write, renamed over the entry once complete.
+    let temp_path = parent.join(format!(
+        ".{file_name}.{}.{}.tmp",
+        std::process::id(),
+        WRITES.fetch_add(1, Ordering::Relaxed)
+    ));
+    if fs::write(&temp_path, content).await.is_err() || fs::rename(&temp_path, path).await.is_err()
+    {
+        let _ = fs::remove_file(&temp_path).await;
+    }
+}
+
+#[async_trait]
+impl<P: AbiProvider> AbiProvider for DiskCacheAbiProvider<P> {
+    async fn get_abi(&self, context: &AbiRequestContext) -> Result<String, QueryBuilderError> {
+        let (abi_path, not_found_path) = match (
+            self.entry_path(context, "json"),
+            self.entry_path(context, "not_found"),
+        ) {
+            (Some(abi_path), Some(not_found_path)) => (abi_path, not_found_path),
+            _ => return self.inner.get_abi(context).await,
+        };
+
+        // entries that don't parse, e.g. left by older versions, are fetched again.
+        if let Ok(abi) = fs::read_to_string(&abi_path).await {
+            if is_abi(&abi) {
+                return Ok(abi);
+            }
+        }
+        if self.is_known_missing(&not_found_path).await {
+            return Err(QueryBuilderError::NoAbiFoundForContract(
+                context.address.to_string(),
+            ));
+        }
+
+        match self.inner.get_abi(context).await {
+            Ok(abi) => {
+                if is_abi(&abi) {
+                    write_entry(&abi_path, &abi).await;
+                }
+                Ok(abi)
+            }
+            Err(QueryBuilderError::NoAbiFoundForContract(address)) => {
+                if self.negative_ttl.is_some() {
+                    write_entry(&not_found_path, &unix_time().to_string()).await;
+                }
+                Err(QueryBuilderError::NoAbiFoundForContract(address))
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::{
+        str::FromStr,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+    };
+
+    use alloy::primitives::Address;
+
+    const VERIFIED: &str = "0xAc1D3D7A8878E655cBb063D58E453540641f4117";
+    const UNVERIFIED: &str = "0xdadB0d80178819F2319190D340ce9A924f783711";
+    const BROKEN: &str = "0x73f7b1184B5cD361cC0f7654998953E2a251dd58";
+
+    fn context(address: &str, chain_id: u64) -> AbiRequestContext {
+        AbiRequestContext::new(Address::from_str(address).unwrap()).with_chain_id(chain_id)
+    }
+
+    #[derive(Clone, Default)]
+    struct CountingProvider {
+        calls: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl AbiProvider for CountingProvider {
+        async fn get_abi(&self, context: &AbiRequestContext) -> Result<String, QueryBuilderError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            if context.address.to_string() == VERIFIED {
+                Ok("[]".into())
+            } else if context.address.to_string() == BROKEN {
+                Ok("<html>rate limited</html>".into())
+            } else {
+                Err(QueryBuilderError::NoAbiFoundForContract(
+                    context.address.to_string(),
+                ))
+            }
+        }
+    }
+
+    impl CountingProvider {
+        fn calls(&self) -> usize {
+            self.calls.load(Ordering::SeqCst)
+        }
+    }
+
+    #[tokio::test]
+    async fn abis_persist_across_instances_per_chain() {
+        let dir = tempfile::tempdir().unwrap();
+        let inner = CountingProvider::default();
+
+        let cache = DiskCacheAbiProvider::new(inner.clone(), dir.path());
+        assert_eq!(
+            cache.get_abi(&context(VERIFIED, 11155111)).await.unwrap(),
+            "[]"
+        );
+        assert!(dir
+            .path()
+            .join("11155111/0xac1d3d7a8878e655cbb063d58e453540641f4117.json")
+            .exists());
+
+        // a new instance, like another process, reads it back from disk.
+        let cache = DiskCacheAbiProvider::new(inner.clone(), dir.path());
+        assert_eq!(
+            cache.get_abi(&context(VERIFIED, 11155111)).await.unwrap(),
+            "[]"
+        );
+        assert_eq!(inner.calls(), 1);
+
+        // the same address on another chain is a different contract.
+        cache.get_abi(&context(VERIFIED, 1)).await.unwrap();
+        assert_eq!(inner.calls(), 2);
+
+        // without a chain id there is nothing safe to key on.
+        let without_chain = AbiRequestContext::new(Address::from_str(VERIFIED).unwrap());
+        cache.get_abi(&without_chain).await.unwrap();
+        cache.get_abi(&without_chain).await.unwrap();
+        assert_eq!(inner.calls(), 4);
+    }
+
+    #[tokio::test]
+    async fn not_found_is_cached_only_with_a_negative_ttl() {
+        let dir = tempfile::tempdir().unwrap();
+        let inner = CountingProvider::default();
+
+        let cache = DiskCacheAbiProvider::new(inner.clone(), dir.path());
+        for _ in 0..2 {
+            assert!(cache.get_abi(&context(UNVERIFIED, 11155111)).await.is_err());
+        }
+        assert_eq!(inner.calls(), 2);
+
+        let cache = DiskCacheAbiProvider::new(inner.clone(), dir.path())
+            .with_negative_ttl(Duration::from_secs(3600));
+        for _ in 0..2 {
+            assert!(matches!(
+                cache.get_abi(&context(UNVERIFIED, 11155111)).await,
+                Err(QueryBuilderError::NoAbiFoundForContract(address)) if address == UNVERIFIED
+            ));
+        }
+        assert_eq!(inner.calls(), 3);
+    }
+
+    #[tokio::test]
+    async fn expired_negative_entries_are_looked_up_again() {
+        let dir = tempfile::tempdir().unwrap();
+        let inner = CountingProvider::default();
+        let cache = DiskCacheAbiProvider::new(inner.clone(), dir.path())
+            .with_negative_ttl(Duration::from_secs(60));
+
+        let entry = dir
+            .path()
+            .join("11155111/0xdadb0d80178819f2319190d340ce9a924f783711.not_found");
+        write_entry(&entry, &(unix_time() - 120).to_string()).await;
+
+        assert!(cache.get_abi(&context(UNVERIFIED, 11155111)).await.is_err());
+        assert_eq!(inner.calls(), 1);
+    }
+
+    #[tokio::test]
+    async fn only_complete_abis_are_cached() {
+        let dir = tempfile::tempdir().unwrap();
+        let inner = CountingProvider::default();
+        let cache = DiskCacheAbiProvider::new(inner.clone(), dir.path());
+
+        // passed on for the caller to report, but not persisted.
+        for _ in 0..2 {
+            assert_eq!(
+                cache.get_abi(&context(BROKEN, 11155111)).await.unwrap(),
+                "<html>rate limited</html>"
+            );
+        }
+        assert_eq!(inner.calls(), 2);
+
+        // a truncated entry, as a crashed writer could have left before, is replaced.
+        let entry = dir
+            .path()
+            .join("11155111/0xac1d3d7a8878e655cbb063d58e453540641f4117.json");
+        std::fs::create_dir_all(entry.parent().unwrap()).unwrap();
+        std::fs::write(&entry, "[{\"type\":\"func").unwrap();
+        assert_eq!(
+            cache.get_abi(&context(VERIFIED, 11155111)).await.unwrap(),
+            "[]"
+        );
+        assert_eq!(std::fs::read_to_string(&entry).unwrap(), "[]");
+        assert_eq!(inner.calls(), 3);
+
+        // no temporary file is left behind.
+        let names: Vec<String> = std::fs::read_dir(entry.parent().unwrap())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(names, ["0xac1d3d7a8878e655cbb063d58e453540641f4117.json"]);
+    }
+
+    #[tokio::test]
+    async fn per_block_cache_keys_entries_on_the_block() {
+        let dir = tempfile::tempdir().unwrap();
+        let inner = CountingProvider::default();
+        let cache = DiskCacheAbiProvider::new(inner.clone(), dir.path()).with_per_block_cache(true);
+
+        for block_number in [100, 200, 100] {
+            cache
+                .get_abi(&context(VERIFIED, 11155111).with_block_number(block_number))
+                .await
+                .unwrap();
+        }
+        assert_eq!(inner.calls(), 2);
+        assert!(dir
+            .path()
+            .join("11155111/0xac1d3d7a8878e655cbb063d58e453540641f4117/200.json")
+            .exists());
+
+        // without a block the answer could be the one of any height.
+        cache.get_abi(&context(VERIFIED, 11155111)).await.unwrap();
+        assert_eq!(inner.calls(), 3);
+    }
+}
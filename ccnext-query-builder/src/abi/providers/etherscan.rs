@@ -0,0 +1,236 @@
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::rate_limiter::RateLimiter;
+use crate::abi::{
+    models::{AbiRequestContext, QueryBuilderError},
+    query_builder::AbiProvider,
+};
+
+pub const ETHERSCAN_V2_API_URL: &str = "https://api.etherscan.io/v2/api";
+
+/// [`AbiProvider`] for explorers serving the Etherscan `module=contract&action=getabi`
+/// API. Blockscout instances expose the same endpoint under `/api`.
+pub struct EtherscanAbiProvider {
+    client: reqwest::Client,
+    base_url: String,
+    api_key: Option<String>,
+    chain_id: Option<u64>,
+    rate_limiter: Option<RateLimiter>,
+}
+
+#[derive(Deserialize)]
+struct EtherscanResponse {
+    status: String,
+    message: String,
+    result: Option<Value>,
+}
+
+impl EtherscanAbiProvider {
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            base_url: base_url.into(),
+            api_key: None,
+            chain_id: None,
+            rate_limiter: None,
+        }
+    }
+
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    /// Sent as `chainid`, required by the Etherscan V2 multichain API. Only used for
+    /// requests whose context doesn't carry a chain id.
+    pub fn with_chain_id(mut self, chain_id: u64) -> Self {
+        self.chain_id = Some(chain_id);
+        self
+    }
+
+    /// Free Etherscan keys are limited to 5 requests per second.
+    pub fn with_rate_limit(mut self, requests_per_second: u32) -> Self {
+        self.rate_limiter = Some(RateLimiter::per_second(requests_per_second));
+        self
+    }
+
+    pub fn with_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+}
+
+#[async_trait]
+impl AbiProvider for EtherscanAbiProvider {
+    async fn get_abi(&self, context: &AbiRequestContext) -> Result<String, QueryBuilderError> {
+        let contract_address = context.address.to_string();
+        let retrieval_failed =
+            |error_message: String| QueryBuilderError::ContractAbiRetrievalFailed {
+                contract_addr: contract_address.clone(),
+                error_message,
+            };
+
+        let mut query = vec![
+            ("module", "contract".to_string()),
+            ("action", "getabi".to_string()),
+            ("address", contract_address.clone()),
+        ];
+        if let Some(chain_id) = context.chain_id.or(self.chain_id) {
+            query.push(("chainid", chain_id.to_string()));
+        }
+        if let Some(api_key) = &self.api_key {
+            query.push(("apikey", api_key.clone()));
+        }
+
+        if let Some(rate_limiter) = &self.rate_limiter {
+            rate_limiter.wait().await;
+        }
+
+        let response = self
+            .client
+            .get(&self.base_url)
+            .query(&query)
+            .send()
+            .await
+            .map_err(|e| retrieval_failed(e.to_string()))?;
+
+        let status = response.status();
+        let body = response
+            .text()
+            .await
+            .map_err(|e| retrieval_failed(e.to_string()))?;
+        if !status.is_success() {
+            return Err(retrieval_failed(format!("HTTP {status}: {body}")));
+        }
+
+        let parsed: EtherscanResponse =
+            serde_json::from_str(&body).map_err(|e| retrieval_failed(e.to_string()))?;
+
+        match (parsed.status.as_str(), parsed.result) {
+            ("1", Some(Value::String(abi))) => Ok(abi),
+            (_, result) => {
+                let result = match result {
+                    Some(Value::String(text)) => text,
+                    Some(other) => other.to_string(),
+                    None => String::new(),
+                };
+
+                // Etherscan puts the reason in `result`, Blockscout in `message`.
+                let reason = format!("{}: {}", parsed.message, result);
+                if reason.to_lowercase().contains("not verified") {
+                    Err(QueryBuilderError::NoAbiFoundForContract(contract_address))
+                } else {
+                    Err(retrieval_failed(reason))
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::{str::FromStr, time::Instant};
+
+    use alloy::primitives::Address;
+
+    use crate::mock_http::MockHttpServer;
+
+    const CONTRACT: &str = "0xAc1D3D7A8878E655cBb063D58E453540641f4117";
+    const ABI: &str = r#"[{"type":"function","name":"burn","inputs":[{"name":"value","type":"uint256"}],"outputs":[],"stateMutability":"nonpayable"}]"#;
+
+    fn context() -> AbiRequestContext {
+        AbiRequestContext::new(Address::from_str(CONTRACT).unwrap())
+    }
+
+    #[tokio::test]
+    async fn verified_contract_returns_abi() {
+        let server = MockHttpServer::start(|_| {
+            let body = serde_json::json!({ "status": "1", "message": "OK", "result": ABI });
+            (200, body.to_string())
+        })
+        .await;
+
+        let provider = EtherscanAbiProvider::new(format!("{}/v2/api", server.base_url()))
+            .with_api_key("KEY")
+            .with_chain_id(11155111);
+
+        let abi = provider.get_abi(&context()).await.unwrap();
+        assert_eq!(abi, ABI);
+
+        // the chain of the transaction being queried wins over the configured one.
+        provider.get_abi(&context().with_chain_id(1)).await.unwrap();
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(
+            requests[0],
+            format!("/v2/api?module=contract&action=getabi&address={CONTRACT}&chainid=11155111&apikey=KEY")
+        );
+        assert!(requests[1].contains("&chainid=1&"));
+    }
+
+    #[tokio::test]
+    async fn unverified_contract_maps_to_no_abi_found() {
+        // Etherscan and Blockscout shaped answers.
+        for body in [
+            r#"{"status":"0","message":"NOTOK","result":"Contract source code not verified"}"#,
+            r#"{"status":"0","message":"Contract source code not verified","result":null}"#,
+        ] {
+            let server = MockHttpServer::start(move |_| (200, body.to_string())).await;
+            let provider = EtherscanAbiProvider::new(format!("{}/api", server.base_url()));
+
+            match provider.get_abi(&context()).await {
+                Err(QueryBuilderError::NoAbiFoundForContract(address)) => {
+                    assert_eq!(address, CONTRACT)
+                }
+                other => panic!("expected NoAbiFoundForContract, got {other:?}"),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn other_failures_map_to_retrieval_failed() {
+        let server = MockHttpServer::start(|target| {
+            if target.contains("apikey=BAD") {
+                (
+                    200,
+                    r#"{"status":"0","message":"NOTOK","result":"Invalid API Key"}"#.into(),
+                )
+            } else {
+                (502, "bad gateway".into())
+            }
+        })
+        .await;
+
+        for api_key in ["BAD", "ANY"] {
+            let provider = EtherscanAbiProvider::new(server.base_url()).with_api_key(api_key);
+            assert!(matches!(
+                provider.get_abi(&context()).await,
+                Err(QueryBuilderError::ContractAbiRetrievalFailed { .. })
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn rate_limit_spaces_out_requests() {
+        let server = MockHttpServer::start(|_| {
+            let body = serde_json::json!({ "status": "1", "message": "OK", "result": ABI });
+            (200, body.to_string())
+        })
+        .await;
+        let provider = EtherscanAbiProvider::new(server.base_url()).with_rate_limit(10);
+
+        let started = Instant::now();
+        for _ in 0..3 {
+            provider.get_abi(&context()).await.unwrap();
+        }
+
+        // the first request goes out immediately, the other two wait 100ms each.
+        assert!(started.elapsed().as_millis() >= 200);
+        assert_eq!(server.requests().len(), 3);
+    }
+}
@@ -0,0 +1,150 @@
+use async_trait::async_trait;
+
+use crate::abi::{
+    models::{AbiRequestContext, QueryBuilderError},
+    query_builder::AbiProvider,
+};
+
+/// Asks each provider in turn until one returns an ABI, e.g. local artifacts, then
+/// Sourcify, then Etherscan.
+pub struct FallbackAbiProvider {
+    providers: Vec<Box<dyn AbiProvider>>,
+}
+
+impl FallbackAbiProvider {
+    pub fn new() -> Self {
+        Self {
+            providers: Vec::new(),
+        }
+    }
+
+    pub fn with_provider(mut self, provider: impl AbiProvider + 'static) -> Self {
+        self.providers.push(Box::new(provider));
+        self
+    }
+}
+
+impl Default for FallbackAbiProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl AbiProvider for FallbackAbiProvider {
+    /// Returns [`QueryBuilderError::NoAbiFoundForContract`] only when every provider said
+    /// so. If any of them failed for another reason the last such error is returned
+    /// instead, since the ABI might well exist.
+    async fn get_abi(&self, context: &AbiRequestContext) -> Result<String, QueryBuilderError> {
+        let mut last_failure = None;
+
+        for provider in &self.providers {
+            match provider.get_abi(context).await {
+                Ok(abi) => return Ok(abi),
+                Err(QueryBuilderError::NoAbiFoundForContract(_)) => {}
+                Err(e) => last_failure = Some(e),
+            }
+        }
+
+        Err(
+            last_failure.unwrap_or(QueryBuilderError::NoAbiFoundForContract(
+                context.address.to_string(),
+            )),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::{
+        str::FromStr,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+    };
+
+    use alloy::primitives::Address;
+
+    const CONTRACT: &str = "0xAc1D3D7A8878E655cBb063D58E453540641f4117";
+
+    fn context() -> AbiRequestContext {
+        AbiRequestContext::new(Address::from_str(CONTRACT).unwrap())
+    }
+
+    struct StaticProvider {
+        answer: fn(String) -> Result<String, QueryBuilderError>,
+        calls: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl AbiProvider for StaticProvider {
+        async fn get_abi(&self, context: &AbiRequestContext) -> Result<String, QueryBuilderError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            (self.answer)(context.address.to_string())
+        }
+    }
+
+    fn provider(
+        answer: fn(String) -> Result<String, QueryBuilderError>,
+    ) -> (StaticProvider, Arc<AtomicUsize>) {
+        let calls = Arc::new(AtomicUsize::new(0));
+        (
+            StaticProvider {
+                answer,
+                calls: calls.clone(),
+            },
+            calls,
+        )
+    }
+
+    fn not_found(address: String) -> Result<String, QueryBuilderError> {
+        Err(QueryBuilderError::NoAbiFoundForContract(address))
+    }
+
+    fn failing(address: String) -> Result<String, QueryBuilderError> {
+        Err(QueryBuilderError::ContractAbiRetrievalFailed {
+            contract_addr: address,
+            error_message: "HTTP 502".into(),
+        })
+    }
+
+    #[tokio::test]
+    async fn first_provider_with_an_abi_wins() {
+        let (local, local_calls) = provider(not_found);
+        let (sourcify, sourcify_calls) = provider(|_| Ok("[]".into()));
+        let (etherscan, etherscan_calls) = provider(|_| Ok("unused".into()));
+
+        let chain = FallbackAbiProvider::new()
+            .with_provider(local)
+            .with_provider(sourcify)
+            .with_provider(etherscan);
+
+        assert_eq!(chain.get_abi(&context()).await.unwrap(), "[]");
+        assert_eq!(local_calls.load(Ordering::SeqCst), 1);
+        assert_eq!(sourcify_calls.load(Ordering::SeqCst), 1);
+        assert_eq!(etherscan_calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn failures_take_precedence_over_not_found() {
+        let (local, _) = provider(not_found);
+        let (etherscan, _) = provider(failing);
+        let chain = FallbackAbiProvider::new()
+            .with_provider(local)
+            .with_provider(etherscan);
+        assert!(matches!(
+            chain.get_abi(&context()).await,
+            Err(QueryBuilderError::ContractAbiRetrievalFailed { .. })
+        ));
+
+        let (local, _) = provider(not_found);
+        let chain = FallbackAbiProvider::new().with_provider(local);
+        assert!(matches!(
+            chain.get_abi(&context()).await,
+            Err(QueryBuilderError::NoAbiFoundForContract(address)) if address == CONTRACT
+        ));
+    }
+}
@@ -0,0 +1,13 @@
+pub mod artifacts;
+#[cfg(feature = "disk-cache")]
+pub mod disk_cache;
+#[cfg(feature = "etherscan")]
+pub mod etherscan;
+pub mod fallback;
+pub mod proxy;
+#[cfg(feature = "etherscan")]
+mod rate_limiter;
+#[cfg(feature = "sourcify")]
+pub mod sourcify;
+#[cfg(feature = "timeout")]
+pub mod timeout;
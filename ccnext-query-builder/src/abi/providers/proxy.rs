@@ -0,0 +1,449 @@
+use alloy::{
+    eips::BlockId,
+    json_abi::{AbiItem, JsonAbi},
+    primitives::{b256, Address, Bytes, B256},
+    providers::{DynProvider, Provider},
+    rpc::types::TransactionRequest,
+};
+use async_trait::async_trait;
+
+use crate::abi::{
+    models::{AbiRequestContext, QueryBuilderError},
+    query_builder::AbiProvider,
+};
+
+/// `keccak256("eip1967.proxy.implementation") - 1`
+pub const EIP1967_IMPLEMENTATION_SLOT: B256 =
+    b256!("360894a13ba1a3210667c828492db98dca3e2076cc3735a920a3ca505d382bbc");
+/// `keccak256("eip1967.proxy.beacon") - 1`
+pub const EIP1967_BEACON_SLOT: B256 =
+    b256!("a3f0ad74e5423aebfd80d3ef4346578335a9a72aeaee59ff6cb3582b35133d50");
+/// `keccak256("PROXIABLE")`
+pub const EIP1822_PROXIABLE_SLOT: B256 =
+    b256!("c5f16f0fcc639fa48a6947836d9850f504798523bf8c9a3a87d5876cf622bcf7");
+
+/// `implementation()` on an EIP-1967 beacon.
+const BEACON_IMPLEMENTATION_SELECTOR: [u8; 4] = [0x5c, 0x60, 0xda, 0x1b];
+
+/// EIP-1167 minimal proxy runtime code, around the 20 byte implementation address.
+const EIP1167_PREFIX: [u8; 10] = [0x36, 0x3d, 0x3d, 0x37, 0x3d, 0x3d, 0x3d, 0x36, 0x3d, 0x73];
+const EIP1167_SUFFIX: [u8; 15] = [
+    0x5a, 0xf4, 0x3d, 0x82, 0x80, 0x3e, 0x90, 0x3d, 0x91, 0x60, 0x2b, 0x57, 0xfd, 0x5b, 0xf3,
+];
+
+/// How a proxy points at its implementation.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ProxyKind {
+    Eip1967,
+    Eip1967Beacon { beacon: Address },
+    Eip1822,
+    Eip1167,
+}
+
+/// [`AbiProvider`] decorator that looks through proxies. The proxy and implementation
+/// are read at the block of the transaction being queried, and the implementation ABI is
+/// merged with the proxy's own, so both the forwarded calls and the proxy's events
+/// (e.g. `Upgraded`) can be found.
+///
+/// The answer changes when the proxy is upgraded, so caches belong underneath it, around
+/// the explorer, e.g. `ProxyResolvingAbiProvider::new(DiskCacheAbiProvider::new(explorer,
+/// dir), rpc)`. Caches wrapping it have to be keyed on the block, see
+/// `DiskCacheAbiProvider::with_per_block_cache` (`disk-cache` feature) and
+/// [`crate::abi::registry::AbiRegistry::with_per_block_cache`].
+pub struct ProxyResolvingAbiProvider<P> {
+    inner: P,
+    provider: DynProvider,
+    max_depth: usize,
+}
+
+impl<P> ProxyResolvingAbiProvider<P> {
+    pub fn new<R: Provider + 'static>(inner: P, provider: R) -> Self {
+        Self {
+            inner,
+            provider: provider.erased(),
+            max_depth: 3,
+        }
+    }
+
+    /// How many proxies are followed when an implementation is itself a proxy, e.g. a
+    /// minimal clone of an EIP-1967 proxy.
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Returns the implementation `context.address` forwards to, `None` if it isn't a
+    /// recognized proxy.
+    pub async fn resolve_implementation(
+        &self,
+        context: &AbiRequestContext,
+    ) -> Result<Option<(ProxyKind, Address)>, QueryBuilderError> {
+        let code = self
+            .provider
+            .get_code_at(context.address)
+            .block_id(block_id(context))
+            .await
+            .map_err(|e| rpc_failed(context, e))?;
+        if code.is_empty() {
+            return Ok(None);
+        }
+        if let Some(implementation) = eip1167_implementation(&code) {
+            return Ok(Some((ProxyKind::Eip1167, implementation)));
+        }
+
+        if let Some(implementation) = self
+            .read_address_slot(context, EIP1967_IMPLEMENTATION_SLOT)
+            .await?
+        {
+            return Ok(Some((ProxyKind::Eip1967, implementation)));
+        }
+
+        if let Some(beacon) = self.read_address_slot(context, EIP1967_BEACON_SLOT).await? {
+            let call = TransactionRequest::default()
+                .to(beacon)
+                .input(Bytes::from(BEACON_IMPLEMENTATION_SELECTOR).into());
+            let output = self
+                .provider
+                .call(&call)
+                .block(block_id(context))
+                .await
+                .map_err(|e| rpc_failed(context, e))?;
+
+            return Ok(word_to_address(&output)
+                .map(|implementation| (ProxyKind::Eip1967Beacon { beacon }, implementation)));
+        }
+
+        Ok(self
+            .read_address_slot(context, EIP1822_PROXIABLE_SLOT)
+            .await?
+            .map(|implementation| (ProxyKind::Eip1822, implementation)))
+    }
+
+    async fn read_address_slot(
+        &self,
+        context: &AbiRequestContext,
+        slot: B256,
+    ) -> Result<Option<Address>, QueryBuilderError> {
+        let value = self
+            .provider
+            .get_storage_at(context.address, slot.into())
+            .block_id(block_id(context))
+            .await
+            .map_err(|e| rpc_failed(context, e))?;
+
+        Ok(word_to_address(&value.to_be_bytes::<32>()))
+    }
+}
+
+impl<P: AbiProvider> ProxyResolvingAbiProvider<P> {
+    async fn get_abi_at_depth(
+        &self,
+        context: &AbiRequestContext,
+        depth: usize,
+    ) -> Result<Option<JsonAbi>, QueryBuilderError> {
+        let own_abi = match self.inner.get_abi(context).await {
+            Ok(abi_raw) => Some(parse_abi(context, &abi_raw)?),
+            // unverified proxies are common, minimal clones are never verified.
+            Err(QueryBuilderError::NoAbiFoundForContract(_)) => None,
+            Err(e) => return Err(e),
+        };
+
+        if depth >= self.max_depth {
+            return Ok(own_abi);
+        }
+
+        let implementation = match self.resolve_implementation(context).await? {
+            Some((_, implementation)) => implementation,
+            None => return Ok(own_abi),
+        };
+
+        let implementation_context = AbiRequestContext {
+            address: implementation,
+            ..*context
+        };
+        let implementation_abi =
+            Box::pin(self.get_abi_at_depth(&implementation_context, depth + 1)).await?;
+
+        Ok(match (implementation_abi, own_abi) {
+            (Some(implementation_abi), Some(own_abi)) => {
+                Some(merge_abis(implementation_abi, own_abi))
+            }
+            (implementation_abi, own_abi) => implementation_abi.or(own_abi),
+        })
+    }
+}
+
+#[async_trait]
+impl<P: AbiProvider> AbiProvider for ProxyResolvingAbiProvider<P> {
+    async fn get_abi(&self, context: &AbiRequestContext) -> Result<String, QueryBuilderError> {
+        match self.get_abi_at_depth(context, 0).await? {
+            Some(abi) => serde_json::to_string(&abi).map_err(|e| {
+                QueryBuilderError::ContractAbiRetrievalFailed {
+                    contract_addr: context.address.to_string(),
+                    error_message: e.to_string(),
+                }
+            }),
+            None => Err(QueryBuilderError::NoAbiFoundForContract(
+                context.address.to_string(),
+            )),
+        }
+    }
+}
+
+fn block_id(context: &AbiRequestContext) -> BlockId {
+    match context.block_number {
+        Some(block_number) => BlockId::number(block_number),
+        None => BlockId::latest(),
+    }
+}
+
+fn rpc_failed(context: &AbiRequestContext, error: impl ToString) -> QueryBuilderError {
+    QueryBuilderError::ContractAbiRetrievalFailed {
+        contract_addr: context.address.to_string(),
+        error_message: error.to_string(),
+    }
+}
+
+fn parse_abi(context: &AbiRequestContext, abi_raw: &str) -> Result<JsonAbi, QueryBuilderError> {
+    JsonAbi::from_json_str(abi_raw).map_err(|_| {
+        QueryBuilderError::FailedToParseAbi(context.address.to_string(), abi_raw.to_string())
+    })
+}
+
+/// An address stored in a 32 byte word, `None` when the word is zero.
+fn word_to_address(word: &[u8]) -> Option<Address> {
+    if word.len() != 32 || word.iter().all(|b| *b == 0) {
+        return None;
+    }
+    Some(Address::from_slice(&word[12..]))
+}
+
+fn eip1167_implementation(code: &[u8]) -> Option<Address> {
+    let rest = code.strip_prefix(&EIP1167_PREFIX[..])?;
+    let (implementation, suffix) = rest.split_at_checked(20)?;
+    (suffix == EIP1167_SUFFIX).then(|| Address::from_slice(implementation))
+}
+
+/// Proxy functions, events and errors whose selector clashes with one of the
+/// implementation are dropped: `JsonAbi` orders items by name, so both would otherwise be
+/// candidates for the same selector.
+fn merge_abis(implementation_abi: JsonAbi, proxy_abi: JsonAbi) -> JsonAbi {
+    let mut items: Vec<AbiItem<'static>> = implementation_abi.into_items().collect();
+    let implementation_selectors: Vec<Vec<u8>> = items.iter().filter_map(selector_of).collect();
+    for item in proxy_abi.into_items() {
+        let clashes =
+            selector_of(&item).is_some_and(|selector| implementation_selectors.contains(&selector));
+        if !clashes && !items.contains(&item) {
+            items.push(item);
+        }
+    }
+    items.into_iter().collect()
+}
+
+fn selector_of(item: &AbiItem<'_>) -> Option<Vec<u8>> {
+    match item {
+        AbiItem::Function(function) => Some(function.selector().to_vec()),
+        AbiItem::Error(error) => Some(error.selector().to_vec()),
+        AbiItem::Event(event) if !event.anonymous => Some(event.selector().to_vec()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use serde_json::{json, Value};
+
+    use crate::mock_transport::MockTransport;
+
+    const PROXY: Address = Address::with_last_byte(0x01);
+    const IMPLEMENTATION: Address = Address::with_last_byte(0x02);
+    const BEACON: Address = Address::with_last_byte(0x03);
+
+    const PROXY_ABI: &str = r#"[{"type":"event","name":"Upgraded","anonymous":false,"inputs":[{"name":"implementation","type":"address","indexed":true}]}]"#;
+    const IMPLEMENTATION_ABI: &str = r#"[{"type":"function","name":"burn","inputs":[{"name":"value","type":"uint256"}],"outputs":[],"stateMutability":"nonpayable"}]"#;
+
+    /// The implementation always has an ABI, the proxy only when `proxy_verified`.
+    struct StaticAbis {
+        proxy_verified: bool,
+    }
+
+    #[async_trait]
+    impl AbiProvider for StaticAbis {
+        async fn get_abi(&self, context: &AbiRequestContext) -> Result<String, QueryBuilderError> {
+            match context.address {
+                IMPLEMENTATION => Ok(IMPLEMENTATION_ABI.into()),
+                PROXY if self.proxy_verified => Ok(PROXY_ABI.into()),
+                _ => Err(QueryBuilderError::NoAbiFoundForContract(
+                    context.address.to_string(),
+                )),
+            }
+        }
+    }
+
+    fn word(address: Address) -> Value {
+        json!(B256::left_padding_from(address.as_slice()))
+    }
+
+    fn zero() -> Value {
+        json!(B256::ZERO)
+    }
+
+    /// What a node answers for the implementation, which is not a proxy itself.
+    fn push_plain_contract(transport: &MockTransport) {
+        transport
+            .push_response("eth_getCode", json!("0x6080604052"))
+            .push_response("eth_getStorageAt", zero())
+            .push_response("eth_getStorageAt", zero())
+            .push_response("eth_getStorageAt", zero());
+    }
+
+    fn context() -> AbiRequestContext {
+        AbiRequestContext::new(PROXY)
+            .with_chain_id(11155111)
+            .with_block_number(0x6c1a2b)
+    }
+
+    #[tokio::test]
+    async fn eip1967_proxy_is_merged_with_its_implementation() {
+        let transport = MockTransport::default();
+        transport
+            .push_response("eth_getCode", json!("0x6080604052"))
+            .push_response("eth_getStorageAt", word(IMPLEMENTATION));
+        push_plain_contract(&transport);
+
+        let provider = ProxyResolvingAbiProvider::new(
+            StaticAbis {
+                proxy_verified: true,
+            },
+            transport.provider(),
+        );
+        let abi = JsonAbi::from_json_str(&provider.get_abi(&context()).await.unwrap()).unwrap();
+        assert!(abi.function("burn").is_some());
+        assert!(abi.event("Upgraded").is_some());
+
+        // everything is read at the block of the transaction.
+        let requests = transport.requests();
+        assert_eq!(
+            requests[1].1,
+            json!([PROXY, EIP1967_IMPLEMENTATION_SLOT, "0x6c1a2b"])
+        );
+        assert!(requests
+            .iter()
+            .all(|(_, params)| params[2] == "0x6c1a2b" || params[1] == "0x6c1a2b"));
+    }
+
+    #[tokio::test]
+    async fn beacon_proxy_asks_the_beacon_for_the_implementation() {
+        let transport = MockTransport::default();
+        transport
+            .push_response("eth_getCode", json!("0x6080604052"))
+            .push_response("eth_getStorageAt", zero())
+            .push_response("eth_getStorageAt", word(BEACON))
+            .push_response("eth_call", word(IMPLEMENTATION));
+        push_plain_contract(&transport);
+
+        let provider = ProxyResolvingAbiProvider::new(
+            StaticAbis {
+                proxy_verified: false,
+            },
+            transport.provider(),
+        );
+        assert_eq!(
+            provider.resolve_implementation(&context()).await.unwrap(),
+            Some((ProxyKind::Eip1967Beacon { beacon: BEACON }, IMPLEMENTATION))
+        );
+
+        let call = transport
+            .requests()
+            .into_iter()
+            .find(|(method, _)| method == "eth_call")
+            .unwrap();
+        assert_eq!(call.1[0]["to"], json!(BEACON));
+        assert_eq!(call.1[0]["input"], json!("0x5c60da1b"));
+    }
+
+    #[tokio::test]
+    async fn unverified_minimal_clone_uses_the_implementation_abi() {
+        let clone_code = format!(
+            "0x363d3d373d3d3d363d73{}5af43d82803e903d91602b57fd5bf3",
+            hex::encode(IMPLEMENTATION)
+        );
+        let transport = MockTransport::default();
+        transport.push_response("eth_getCode", json!(clone_code));
+        push_plain_contract(&transport);
+
+        let provider = ProxyResolvingAbiProvider::new(
+            StaticAbis {
+                proxy_verified: false,
+            },
+            transport.provider(),
+        );
+        let abi = JsonAbi::from_json_str(&provider.get_abi(&context()).await.unwrap()).unwrap();
+        assert!(abi.function("burn").is_some());
+        assert!(abi.event("Upgraded").is_none());
+    }
+
+    #[test]
+    fn proxy_functions_clashing_with_the_implementation_are_dropped() {
+        let implementation_abi = JsonAbi::from_json_str(IMPLEMENTATION_ABI).unwrap();
+        // same selector as `burn(uint256)`, and sorted after it by name.
+        let proxy_abi = JsonAbi::parse([
+            "function collate_propagate_storage(bytes16)",
+            "function upgradeTo(address)",
+            "function burn(uint256 value)",
+        ])
+        .unwrap();
+        let clash = proxy_abi.function("collate_propagate_storage").unwrap()[0].selector();
+        assert_eq!(
+            clash,
+            implementation_abi.function("burn").unwrap()[0].selector()
+        );
+
+        let merged = merge_abis(implementation_abi, proxy_abi);
+        let candidates: Vec<&str> = merged
+            .functions()
+            .filter(|function| function.selector() == clash)
+            .map(|function| function.name.as_str())
+            .collect();
+        assert_eq!(candidates, ["burn"]);
+        assert!(merged.function("upgradeTo").is_some());
+    }
+
+    #[tokio::test]
+    async fn eip1822_and_plain_contracts() {
+        let transport = MockTransport::default();
+        transport
+            .push_response("eth_getCode", json!("0x6080604052"))
+            .push_response("eth_getStorageAt", zero())
+            .push_response("eth_getStorageAt", zero())
+            .push_response("eth_getStorageAt", word(IMPLEMENTATION));
+        push_plain_contract(&transport);
+
+        let provider = ProxyResolvingAbiProvider::new(
+            StaticAbis {
+                proxy_verified: false,
+            },
+            transport.provider(),
+        );
+        assert_eq!(
+            provider.resolve_implementation(&context()).await.unwrap(),
+            Some((ProxyKind::Eip1822, IMPLEMENTATION))
+        );
+        assert_eq!(
+            provider
+                .resolve_implementation(&AbiRequestContext::new(IMPLEMENTATION))
+                .await
+                .unwrap(),
+            None
+        );
+
+        // not a proxy and no ABI of its own.
+        transport.push_response("eth_getCode", json!("0x"));
+        assert!(matches!(
+            provider.get_abi(&context()).await,
+            Err(QueryBuilderError::NoAbiFoundForContract(_))
+        ));
+    }
+}
@@ -0,0 +1,34 @@
+use std::time::Duration;
+
+use tokio::{
+    sync::Mutex,
+    time::{sleep, Instant},
+};
+
+/// Spaces out requests so that at most `requests_per_second` are sent.
+pub(crate) struct RateLimiter {
+    min_interval: Duration,
+    last_request: Mutex<Option<Instant>>,
+}
+
+impl RateLimiter {
+    pub(crate) fn per_second(requests_per_second: u32) -> Self {
+        Self {
+            min_interval: Duration::from_secs(1) / requests_per_second.max(1),
+            last_request: Mutex::new(None),
+        }
+    }
+
+    /// Waits until the next request is allowed. The lock is held while sleeping so
+    /// concurrent callers queue up instead of all firing at once.
+    pub(crate) async fn wait(&self) {
+        let mut last_request = self.last_request.lock().await;
+        if let Some(last) = *last_request {
+            let elapsed = last.elapsed();
+            if elapsed < self.min_interval {
+                sleep(self.min_interval - elapsed).await;
+            }
+        }
+        *last_request = Some(Instant::now());
+    }
+}
@@ -0,0 +1,223 @@
+use async_trait::async_trait;
+use reqwest::StatusCode;
+use serde_json::Value;
+
+use crate::abi::{
+    models::{AbiRequestContext, QueryBuilderError},
+    query_builder::AbiProvider,
+};
+
+pub const SOURCIFY_REPOSITORY_URL: &str = "https://repo.sourcify.dev";
+
+/// [`AbiProvider`] reading `output.abi` out of the `metadata.json` stored by a
+/// Sourcify-compatible repository. Full matches are preferred over partial ones.
+pub struct SourcifyAbiProvider {
+    client: reqwest::Client,
+    base_url: String,
+    chain_id: Option<u64>,
+    allow_partial_matches: bool,
+}
+
+impl SourcifyAbiProvider {
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            base_url: base_url.into(),
+            chain_id: None,
+            allow_partial_matches: true,
+        }
+    }
+
+    /// Only used for requests whose context doesn't carry a chain id.
+    pub fn with_chain_id(mut self, chain_id: u64) -> Self {
+        self.chain_id = Some(chain_id);
+        self
+    }
+
+    /// Partial matches only differ in the metadata hash, so the ABI is usually the same.
+    /// They are accepted by default.
+    pub fn with_partial_matches(mut self, allow_partial_matches: bool) -> Self {
+        self.allow_partial_matches = allow_partial_matches;
+        self
+    }
+
+    pub fn with_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Returns `Ok(None)` when the repository has no metadata for this kind of match.
+    async fn get_metadata(
+        &self,
+        match_type: &str,
+        chain_id: u64,
+        contract_address: &str,
+    ) -> Result<Option<Value>, QueryBuilderError> {
+        let retrieval_failed =
+            |error_message: String| QueryBuilderError::ContractAbiRetrievalFailed {
+                contract_addr: contract_address.to_string(),
+                error_message,
+            };
+
+        let url = format!(
+            "{}/contracts/{}/{}/{}/metadata.json",
+            self.base_url.trim_end_matches('/'),
+            match_type,
+            chain_id,
+            contract_address
+        );
+        let response = self
+            .client
+            .get(url)
+            .send()
+            .await
+            .map_err(|e| retrieval_failed(e.to_string()))?;
+
+        let status = response.status();
+        if status == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+
+        let body = response
+            .text()
+            .await
+            .map_err(|e| retrieval_failed(e.to_string()))?;
+        if !status.is_success() {
+            return Err(retrieval_failed(format!("HTTP {status}: {body}")));
+        }
+
+        serde_json::from_str(&body)
+            .map(Some)
+            .map_err(|e| retrieval_failed(e.to_string()))
+    }
+}
+
+#[async_trait]
+impl AbiProvider for SourcifyAbiProvider {
+    async fn get_abi(&self, context: &AbiRequestContext) -> Result<String, QueryBuilderError> {
+        let contract_address = context.address.to_string();
+        let chain_id = match context.chain_id.or(self.chain_id) {
+            Some(chain_id) => chain_id,
+            None => {
+                return Err(QueryBuilderError::ContractAbiRetrievalFailed {
+                    contract_addr: contract_address,
+                    error_message: "Sourcify needs a chain id".into(),
+                })
+            }
+        };
+
+        let mut metadata = self
+            .get_metadata("full_match", chain_id, &contract_address)
+            .await?;
+        if metadata.is_none() && self.allow_partial_matches {
+            metadata = self
+                .get_metadata("partial_match", chain_id, &contract_address)
+                .await?;
+        }
+
+        let metadata = match metadata {
+            Some(m) => m,
+            None => return Err(QueryBuilderError::NoAbiFoundForContract(contract_address)),
+        };
+
+        match metadata.pointer("/output/abi") {
+            Some(abi @ Value::Array(_)) => Ok(abi.to_string()),
+            _ => Err(QueryBuilderError::ContractAbiRetrievalFailed {
+                contract_addr: contract_address,
+                error_message: "metadata.json has no output.abi".into(),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::{path::PathBuf, str::FromStr};
+
+    use alloy::{json_abi::JsonAbi, primitives::Address};
+
+    use crate::mock_http::MockHttpServer;
+
+    const FULL_MATCH: &str = "0xAc1D3D7A8878E655cBb063D58E453540641f4117";
+    const PARTIAL_MATCH: &str = "0x73f7b1184B5cD361cC0f7654998953E2a251dd58";
+    const UNVERIFIED: &str = "0xdadB0d80178819F2319190D340ce9A924f783711";
+
+    fn context(address: &str) -> AbiRequestContext {
+        AbiRequestContext::new(Address::from_str(address).unwrap()).with_chain_id(11155111)
+    }
+
+    /// Serves `fixtures/sourcify` the way a Sourcify repository would.
+    async fn start_repository() -> MockHttpServer {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("fixtures/sourcify");
+        MockHttpServer::start(move |target| {
+            match std::fs::read_to_string(root.join(target.trim_start_matches('/'))) {
+                Ok(body) => (200, body),
+                Err(_) => (404, r#"{"error":"not found"}"#.into()),
+            }
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn full_match_is_preferred_over_partial_match() {
+        let repository = start_repository().await;
+        let provider = SourcifyAbiProvider::new(repository.base_url());
+
+        let abi = JsonAbi::from_json_str(&provider.get_abi(&context(FULL_MATCH)).await.unwrap())
+            .expect("abi should parse");
+        assert!(abi.function("burn").is_some());
+        assert!(abi.function("staleFunction").is_none());
+        assert_eq!(
+            repository.requests(),
+            vec![format!(
+                "/contracts/full_match/11155111/{FULL_MATCH}/metadata.json"
+            )]
+        );
+    }
+
+    #[tokio::test]
+    async fn partial_match_is_used_when_there_is_no_full_match() {
+        let repository = start_repository().await;
+        let provider = SourcifyAbiProvider::new(repository.base_url());
+
+        let abi = JsonAbi::from_json_str(&provider.get_abi(&context(PARTIAL_MATCH)).await.unwrap())
+            .expect("abi should parse");
+        assert!(abi.event("Deposited").is_some());
+
+        let full_match_only =
+            SourcifyAbiProvider::new(repository.base_url()).with_partial_matches(false);
+        assert!(matches!(
+            full_match_only.get_abi(&context(PARTIAL_MATCH)).await,
+            Err(QueryBuilderError::NoAbiFoundForContract(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn unverified_contract_or_other_chain_maps_to_no_abi_found() {
+        let repository = start_repository().await;
+
+        let provider = SourcifyAbiProvider::new(repository.base_url());
+        assert!(matches!(
+            provider.get_abi(&context(UNVERIFIED)).await,
+            Err(QueryBuilderError::NoAbiFoundForContract(address)) if address == UNVERIFIED
+        ));
+
+        let mainnet = context(FULL_MATCH).with_chain_id(1);
+        assert!(matches!(
+            provider.get_abi(&mainnet).await,
+            Err(QueryBuilderError::NoAbiFoundForContract(_))
+        ));
+
+        // pre EIP-155 transactions carry no chain id, the configured one is used instead.
+        let without_chain = AbiRequestContext::new(mainnet.address);
+        assert!(matches!(
+            provider.get_abi(&without_chain).await,
+            Err(QueryBuilderError::ContractAbiRetrievalFailed { .. })
+        ));
+        let sepolia_default =
+            SourcifyAbiProvider::new(repository.base_url()).with_chain_id(11155111);
+        assert!(sepolia_default.get_abi(&without_chain).await.is_ok());
+    }
+}
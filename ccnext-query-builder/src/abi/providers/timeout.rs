@@ -0,0 +1,80 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+
+use crate::abi::{
+    models::{AbiRequestContext, QueryBuilderError},
+    query_builder::AbiProvider,
+};
+
+/// Fails lookups of the wrapped provider that take longer than `timeout`, so one slow
+/// explorer can't stall a [`super::fallback::FallbackAbiProvider`] chain.
+pub struct TimeoutAbiProvider<P> {
+    inner: P,
+    timeout: Duration,
+}
+
+impl<P> TimeoutAbiProvider<P> {
+    pub fn new(inner: P, timeout: Duration) -> Self {
+        Self { inner, timeout }
+    }
+}
+
+#[async_trait]
+impl<P: AbiProvider> AbiProvider for TimeoutAbiProvider<P> {
+    async fn get_abi(&self, context: &AbiRequestContext) -> Result<String, QueryBuilderError> {
+        match tokio::time::timeout(self.timeout, self.inner.get_abi(context)).await {
+            Ok(result) => result,
+            Err(_) => Err(QueryBuilderError::ContractAbiRetrievalFailed {
+                contract_addr: context.address.to_string(),
+                error_message: format!("timed out after {:?}", self.timeout),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use alloy::primitives::Address;
+
+    struct SlowProvider(Duration);
+
+    #[async_trait]
+    impl AbiProvider for SlowProvider {
+        async fn get_abi(&self, _context: &AbiRequestContext) -> Result<String, QueryBuilderError> {
+            tokio::time::sleep(self.0).await;
+            Ok("[]".into())
+        }
+    }
+
+    #[tokio::test]
+    async fn slow_lookups_time_out() {
+        let provider = TimeoutAbiProvider::new(
+            SlowProvider(Duration::from_secs(5)),
+            Duration::from_millis(20),
+        );
+        match provider
+            .get_abi(&AbiRequestContext::new(Address::ZERO))
+            .await
+        {
+            Err(QueryBuilderError::ContractAbiRetrievalFailed { error_message, .. }) => {
+                assert_eq!(error_message, "timed out after 20ms")
+            }
+            other => panic!("expected a timeout, got {other:?}"),
+        }
+
+        let provider = TimeoutAbiProvider::new(
+            SlowProvider(Duration::from_millis(1)),
+            Duration::from_secs(5),
+        );
+        assert_eq!(
+            provider
+                .get_abi(&AbiRequestContext::new(Address::ZERO))
+                .await
+                .unwrap(),
+            "[]"
+        );
+    }
+}
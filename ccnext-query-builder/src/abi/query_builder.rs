@@ -1,40 +1,60 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, sync::Arc};
 
 use alloy::{
     consensus::Transaction as _,
     dyn_abi::{DecodedEvent, DynSolType, EventExt},
     hex::FromHex,
-    json_abi::JsonAbi,
-    primitives::{map::HashSet, FixedBytes},
+    json_abi::{ContractObject, Function, JsonAbi},
+    primitives::{Address, Bytes, FixedBytes, B256},
+    providers::Provider,
     rpc::types::{Log, Transaction, TransactionReceipt},
+    sol_types::{JsonAbiExt, SolCall, SolEvent},
 };
 use alloy_json_abi::Event;
 use async_trait::async_trait;
 
 use super::{
-    field_mapping::get_all_fields_for_transaction, models::QueryBuilderError,
-    query_builder_for_event::QueryBuilderForEvent, utils::compute_abi_offsets,
+    erc4337::{EntryPointVersion, UserOperationBuilder},
+    field_mapping::get_all_fields_for_transaction,
+    inline_abi::{sol_abi, IntoConstructorAbi, IntoEventAbi, IntoFunctionAbi},
+    log_filter::{typed_filter, EventFilter, MatchedEvent, TypedEventFilter},
+    models::QueryBuilderError,
+    query_builder_for_constructor::QueryBuilderForConstructor,
+    query_builder_for_event::{check_range, QueryBuilderForEvent},
+    registry::{AbiBatch, AbiRegistry},
+    rpc::fetch_transaction_and_receipt,
+    signatures::SignatureDatabase,
+    typed::{TypedEventBuilder, TypedFunctionBuilder},
+    utils::compute_abi_offsets,
 };
 use crate::abi::{
-    models::{FieldMetadata, QueryableFields},
-    query_builder_for_function::QueryBuilderForFunction,
-    utils::{make_offsets_absolute, WORD_SIZE},
+    models::{
+        AbiRequestContext, EventSearchMode, FieldMetadata, NestedCallTarget, NestedCalldata,
+        NormalizedSegments, QueryWarning, QueryableFields,
+    },
+    query_builder_for_function::{
+        calldata_selector_of, check_function_matches_calldata, QueryBuilderForFunction,
+    },
+    utils::{decode_log_exact, make_offsets_absolute, normalize_segments, WORD_SIZE},
 };
 use ccnext_abi_encoding::{abi::abi_encode, common::EncodingVersion};
 
 #[async_trait]
-pub trait AbiProvider {
-    async fn get_abi(&self, contract_address: String) -> Result<String, QueryBuilderError>;
+pub trait AbiProvider: Send + Sync {
+    async fn get_abi(&self, context: &AbiRequestContext) -> Result<String, QueryBuilderError>;
 }
 
 pub struct QueryBuilder {
     tx: Transaction,
     rx: TransactionReceipt,
-    abi_provider: Option<Box<dyn AbiProvider>>,
+    abi_registry: Option<AbiRegistry>,
+    signature_database: Option<Arc<SignatureDatabase>>,
+    chain_id: Option<u64>,
+    event_search_mode: EventSearchMode,
+    warnings: Vec<QueryWarning>,
     _computed_offsets: Vec<FieldMetadata>,
     mapped_offsets: HashMap<QueryableFields, FieldMetadata>,
     selected_offsets: Vec<(usize, usize)>,
-    abi_cache: HashMap<String, JsonAbi>,
 }
 
 fn hex_to_4_bytes(hex: &str) -> Result<[u8; 4], &'static str> {
@@ -52,6 +72,70 @@ fn hex_to_4_bytes(hex: &str) -> Result<[u8; 4], &'static str> {
     Ok(arr)
 }
 
+/// Selector of a `0x` prefixed selector, a canonical signature (`transfer(address,uint256)`)
+/// or a human-readable fragment (`function transfer(address to, uint256 value)`). `None`
+/// for a bare name.
+fn function_selector_of(name_or_signature: &str) -> Result<Option<[u8; 4]>, QueryBuilderError> {
+    if name_or_signature.starts_with("0x") {
+        return match hex_to_4_bytes(name_or_signature) {
+            Ok(selector) => Ok(Some(selector)),
+            Err(_) => Err(QueryBuilderError::FunctionSignatureNameProvidedIsNotValidHex),
+        };
+    }
+    if !name_or_signature.contains('(') {
+        return Ok(None);
+    }
+    let function = name_or_signature.into_function_abi()?;
+    Ok(Some(function.selector().0))
+}
+
+/// Same as [`function_selector_of`], for event topics.
+fn event_selector_of(event_name_or_signature: &str) -> Result<Option<B256>, QueryBuilderError> {
+    if event_name_or_signature.starts_with("0x") {
+        return match FixedBytes::<32>::from_hex(event_name_or_signature) {
+            Ok(selector) => Ok(Some(selector)),
+            Err(_) => Err(QueryBuilderError::EventSignatureNameProvidedIsNotValidHex),
+        };
+    }
+    if !event_name_or_signature.contains('(') {
+        return Ok(None);
+    }
+    let event = event_name_or_signature.into_event_abi()?;
+    Ok(Some(event.selector()))
+}
+
+type AbisAndFailures = (
+    HashMap<Address, JsonAbi>,
+    HashMap<Address, QueryBuilderError>,
+);
+
+fn missing_abi(
+    abi_failures: &HashMap<Address, QueryBuilderError>,
+    address: Address,
+) -> QueryBuilderError {
+    match abi_failures.get(&address) {
+        Some(e) => e.clone(),
+        None => QueryBuilderError::NoAbiFoundForContract(address.to_string()),
+    }
+}
+
+fn single_event(
+    mut events: Vec<(Log, DecodedEvent, usize, Event)>,
+    event: &Event,
+    take_first_if_multiple: bool,
+) -> Result<(Log, DecodedEvent, usize, Event), QueryBuilderError> {
+    match events.len() {
+        0 => Err(QueryBuilderError::FailedToFindEventByNameOrSignature(
+            event.full_signature(),
+        )),
+        1 => Ok(events.remove(0)),
+        _ if take_first_if_multiple => Ok(events.remove(0)),
+        _ => Err(QueryBuilderError::AmbigiousEventMatch(
+            event.full_signature(),
+        )),
+    }
+}
+
 impl QueryBuilder {
     pub fn create_from_transaction(
         tx: Transaction,
@@ -155,103 +239,482 @@ impl QueryBuilder {
             mapped_offsets.insert(field.clone(), offset);
         }
 
+        let chain_id = tx.chain_id();
         Ok(QueryBuilder {
             tx,
             rx,
-            abi_provider: None,
+            abi_registry: None,
+            signature_database: None,
+            chain_id,
+            event_search_mode: EventSearchMode::default(),
+            warnings: Vec::new(),
             mapped_offsets,
             _computed_offsets: computed_offsets.clone(),
             selected_offsets: vec![],
-            abi_cache: HashMap::new(),
         })
     }
 
+    /// Fetches the transaction and its receipt through `provider` and builds the query on top.
+    pub async fn from_tx_hash<P: Provider>(
+        provider: &P,
+        tx_hash: B256,
+        encoding: EncodingVersion,
+    ) -> Result<QueryBuilder, QueryBuilderError> {
+        let (tx, rx) = fetch_transaction_and_receipt(provider, tx_hash).await?;
+        Self::create_from_transaction(tx, rx, encoding)
+    }
+
+    /// Uses `abi_provider` with a cache private to this builder.
     pub fn set_abi_provider(&mut self, abi_provider: Box<dyn AbiProvider>) {
-        self.abi_provider = Some(abi_provider);
+        self.abi_registry = Some(AbiRegistry::from_provider(abi_provider.into()));
+    }
+
+    /// Uses a registry whose cache is shared with every other builder holding a clone of it.
+    pub fn set_abi_registry(&mut self, abi_registry: AbiRegistry) {
+        self.abi_registry = Some(abi_registry);
+    }
+
+    /// Used to decode calldata and logs of contracts the ABI provider has no ABI for.
+    pub fn set_signature_database(&mut self, signature_database: Arc<SignatureDatabase>) {
+        self.signature_database = Some(signature_database);
+    }
+
+    /// How [`Self::find_all_events`], and the event builders relying on it, treat logs they
+    /// can't decode.
+    pub fn set_event_search_mode(&mut self, event_search_mode: EventSearchMode) {
+        self.event_search_mode = event_search_mode;
+    }
+
+    /// Defaults to the chain id of the transaction, which pre EIP-155 transactions lack.
+    pub fn set_chain_id(&mut self, chain_id: u64) {
+        self.chain_id = Some(chain_id);
     }
 
-    pub async fn function_builder(
+    /// What ABI providers are asked for when resolving `address` for this transaction.
+    pub fn abi_request_context(&self, address: Address) -> AbiRequestContext {
+        AbiRequestContext {
+            chain_id: self.chain_id,
+            address,
+            block_number: self.tx.block_number,
+        }
+    }
+
+    pub async fn function_builder<C>(
         &mut self,
         name_or_signature: String,
-        configurator: fn(&mut QueryBuilderForFunction) -> Result<(), QueryBuilderError>,
-    ) -> Result<&mut Self, QueryBuilderError> {
-        if self.tx.inner.input().is_empty() {
-            return Err(QueryBuilderError::RequestingFunctionArgumentOfAnEmptyCalldataTransaction);
+        configurator: C,
+    ) -> Result<&mut Self, QueryBuilderError>
+    where
+        C: FnOnce(&mut QueryBuilderForFunction) -> Result<(), QueryBuilderError> + Send,
+    {
+        let contract_address = self.called_contract()?;
+        let calldata = self.tx.inner.input().clone();
+        let data_field = self.tx_data_field()?;
+        let matched_function = self
+            .find_function(contract_address, &name_or_signature, &calldata)
+            .await?;
+
+        // now that we have a matched function :)
+        // we can create a function builder for it.
+        self.select_from_matched_function(matched_function, calldata, data_field, configurator)?;
+        Ok(self)
+    }
+
+    /// Selects from the calldata of an inner call carried by a `bytes` argument of the
+    /// transaction's function, e.g. the `data` of a Safe `execTransaction` or an element of
+    /// `multicall(bytes[])`. Both functions are looked up like in [`Self::function_builder`],
+    /// the inner one in the ABI of the [`NestedCalldata`] target. Offsets stay absolute.
+    pub async fn nested_function_builder<C>(
+        &mut self,
+        name_or_signature: String,
+        calldata: NestedCalldata,
+        inner_name_or_signature: String,
+        configurator: C,
+    ) -> Result<&mut Self, QueryBuilderError>
+    where
+        C: FnOnce(&mut QueryBuilderForFunction) -> Result<(), QueryBuilderError> + Send,
+    {
+        let contract_address = self.called_contract()?;
+        let tx_calldata = self.tx.inner.input().clone();
+        let data_field = self.tx_data_field()?;
+        let outer_function = self
+            .find_function(contract_address, &name_or_signature, &tx_calldata)
+            .await?;
+
+        let outer_builder = QueryBuilderForFunction::new(outer_function, tx_calldata, data_field);
+        let (inner_calldata, inner_data_field) = outer_builder.nested_calldata(&calldata)?;
+        let target = match calldata.target() {
+            NestedCallTarget::Transaction => contract_address,
+            NestedCallTarget::Argument(name) => outer_builder.address_argument(name)?,
+            NestedCallTarget::Address(address) => *address,
+        };
+
+        let inner_function = self
+            .find_function(target, &inner_name_or_signature, &inner_calldata)
+            .await?;
+        self.select_from_matched_function(
+            inner_function,
+            inner_calldata,
+            inner_data_field,
+            configurator,
+        )?;
+        Ok(self)
+    }
+
+    /// Selects from the user operation at `op_index` of an ERC-4337 `handleOps` transaction.
+    pub fn user_operation_builder<C>(
+        &mut self,
+        version: EntryPointVersion,
+        op_index: usize,
+        configurator: C,
+    ) -> Result<&mut Self, QueryBuilderError>
+    where
+        C: FnOnce(&mut UserOperationBuilder) -> Result<(), QueryBuilderError>,
+    {
+        self.function_builder_with_abi(version.handle_ops(), |builder| {
+            let user_operations = builder.argument_field(0)?;
+            let user_operation = match user_operations.children.get(op_index) {
+                Some(user_operation) => user_operation.clone(),
+                None => return Err(QueryBuilderError::UserOperationNotFound(op_index)),
+            };
+            configurator(&mut UserOperationBuilder::new(builder, user_operation))
+        })
+    }
+
+    /// Selects from the `UserOperationEvent` of the user operation at `op_index`, told apart
+    /// from the events of the other operations by its `userOpHash`.
+    pub fn user_operation_event_builder<C>(
+        &mut self,
+        version: EntryPointVersion,
+        op_index: usize,
+        configurator: C,
+    ) -> Result<&mut Self, QueryBuilderError>
+    where
+        C: FnOnce(&mut QueryBuilderForEvent) -> Result<(), QueryBuilderError>,
+    {
+        let user_operation_hash = self.user_operation_hash(version, op_index)?;
+        let entry_point = self.called_contract()?;
+        self.event_builder_at_address(
+            entry_point,
+            version.user_operation_event(),
+            |log: Log, _, _| log.topics().get(1) == Some(&user_operation_hash),
+            false,
+            configurator,
+        )
+    }
+
+    /// `userOpHash` of the user operation at `op_index` of an ERC-4337 `handleOps` transaction.
+    pub fn user_operation_hash(
+        &self,
+        version: EntryPointVersion,
+        op_index: usize,
+    ) -> Result<B256, QueryBuilderError> {
+        let entry_point = self.called_contract()?;
+        let chain_id = match self.chain_id {
+            Some(chain_id) => chain_id,
+            None => return Err(QueryBuilderError::MissingChainId),
+        };
+
+        check_function_matches_calldata(&version.handle_ops(), self.tx.inner.input())?;
+        let hashes = version.user_operation_hashes(self.tx.inner.input(), entry_point, chain_id)?;
+        match hashes.get(op_index) {
+            Some(hash) => Ok(*hash),
+            None => Err(QueryBuilderError::UserOperationNotFound(op_index)),
         }
+    }
 
-        let contract_address = match self.tx.to() {
-            Some(ca) => ca,
-            None => {
-                return Err(QueryBuilderError::RequestingFunctionArgumentButNoToAddressPresent);
+    /// Selects constructor arguments of a contract creation transaction, with the
+    /// constructor of the created contract's ABI. The arguments follow the
+    /// `creation_bytecode_length` bytes of creation bytecode in the calldata.
+    pub async fn constructor_builder<C>(
+        &mut self,
+        creation_bytecode_length: usize,
+        configurator: C,
+    ) -> Result<&mut Self, QueryBuilderError>
+    where
+        C: FnOnce(&mut QueryBuilderForConstructor) -> Result<(), QueryBuilderError> + Send,
+    {
+        let contract_address = match (self.tx.to(), self.rx.contract_address) {
+            (None, Some(ca)) => ca,
+            _ => return Err(QueryBuilderError::NotAContractCreation),
+        };
+        let abi = self.get_abi_from_provider_cached(contract_address).await?;
+        self.constructor_builder_with_abi(&abi, creation_bytecode_length, configurator)
+    }
+
+    /// Like [`Self::constructor_builder`], with the constructor supplied by the caller.
+    pub fn constructor_builder_with_abi<T, C>(
+        &mut self,
+        constructor: T,
+        creation_bytecode_length: usize,
+        configurator: C,
+    ) -> Result<&mut Self, QueryBuilderError>
+    where
+        T: IntoConstructorAbi,
+        C: FnOnce(&mut QueryBuilderForConstructor) -> Result<(), QueryBuilderError>,
+    {
+        let constructor = constructor.into_constructor_abi()?;
+        if self.tx.to().is_some() {
+            return Err(QueryBuilderError::NotAContractCreation);
+        }
+
+        let calldata = self.tx.inner.input();
+        let arguments = match calldata.get(creation_bytecode_length..) {
+            Some(arguments) => Bytes::copy_from_slice(arguments),
+            None => return Err(QueryBuilderError::CreationBytecodeDoesNotMatch),
+        };
+        let data_field = self.tx_data_field()?;
+
+        let mut builder = QueryBuilderForConstructor::new(
+            constructor,
+            arguments,
+            data_field.offset + creation_bytecode_length,
+        );
+        configurator(&mut builder)?;
+        self.selected_offsets.extend(builder.get_selected_offsets());
+        Ok(self)
+    }
+
+    /// Like [`Self::constructor_builder`], with the constructor and the creation bytecode
+    /// taken from a compiler artifact, a Foundry or Hardhat JSON with `abi` and `bytecode`.
+    pub fn constructor_builder_from_artifact<C>(
+        &mut self,
+        artifact: &str,
+        configurator: C,
+    ) -> Result<&mut Self, QueryBuilderError>
+    where
+        C: FnOnce(&mut QueryBuilderForConstructor) -> Result<(), QueryBuilderError>,
+    {
+        if self.tx.to().is_some() {
+            return Err(QueryBuilderError::NotAContractCreation);
+        }
+
+        let artifact: ContractObject = match serde_json::from_str(artifact) {
+            Ok(artifact) => artifact,
+            Err(e) => return Err(QueryBuilderError::FailedToParseArtifact(e.to_string())),
+        };
+        let (abi, bytecode) = match (artifact.abi, artifact.bytecode) {
+            (Some(abi), Some(bytecode)) => (abi, bytecode),
+            _ => {
+                return Err(QueryBuilderError::FailedToParseArtifact(
+                    "artifact needs both `abi` and `bytecode`".to_string(),
+                ))
             }
         };
+        if !self.tx.inner.input().starts_with(&bytecode) {
+            return Err(QueryBuilderError::CreationBytecodeDoesNotMatch);
+        }
+
+        self.constructor_builder_with_abi(&abi, bytecode.len(), configurator)
+    }
 
-        let data_field = match self.mapped_offsets.get(&QueryableFields::TxData) {
-            Some(t) => t,
-            None => return Err(QueryBuilderError::FailedToFindTxDataField),
+    fn called_contract(&self) -> Result<Address, QueryBuilderError> {
+        if self.tx.inner.input().is_empty() {
+            return Err(QueryBuilderError::RequestingFunctionArgumentOfAnEmptyCalldataTransaction);
         }
-        .clone();
 
-        let abi = self
-            .get_abi_from_provider_cached(contract_address.to_string())
-            .await?;
-        // If signature, then we can get function without ambiguity
-        let matched_function = if name_or_signature.starts_with("0x") {
-            let name_of_signature_bytes = match self::hex_to_4_bytes(name_or_signature.as_str()) {
-                Ok(t) => t,
-                Err(_) => {
-                    return Err(QueryBuilderError::FunctionSignatureNameProvidedIsNotValidHex)
-                }
-            };
+        match self.tx.to() {
+            Some(ca) => Ok(ca),
+            None => Err(QueryBuilderError::RequestingFunctionArgumentButNoToAddressPresent),
+        }
+    }
 
-            match abi
-                .functions()
-                .find(|f| f.selector().0 == name_of_signature_bytes)
-            {
-                Some(t) => t,
-                None => {
-                    return Err(QueryBuilderError::FailedToFindFunctionByNameOrSignature(
-                        name_or_signature,
-                    ))
-                }
+    /// Looks the function of `calldata` up in the ABI of `contract_address`, falling back to
+    /// the signature database when that contract has none.
+    async fn find_function(
+        &mut self,
+        contract_address: Address,
+        name_or_signature: &str,
+        calldata: &[u8],
+    ) -> Result<Function, QueryBuilderError> {
+        let calldata_selector = calldata_selector_of(calldata)?;
+
+        let abi = match self.get_abi_from_provider_cached(contract_address).await {
+            Ok(abi) => abi,
+            Err(e) if self.can_fall_back_to_signature_database(&e) => {
+                return self.find_function_in_signature_database(name_or_signature, calldata);
             }
-        } else {
-            // If name was passed, then we get the function with that name. For now, we error out when
-            // multiple functions are found with the same name.
-            match abi.function(&name_or_signature) {
-                Some(functions) => {
-                    if functions.len() > 1 {
-                        return Err(QueryBuilderError::AmbigiousFunctionMatch(functions.clone()));
+            Err(e) => return Err(e),
+        };
+        // If signature, then we can get function without ambiguity
+        let matched_function =
+            if let Some(name_of_signature_bytes) = function_selector_of(name_or_signature)? {
+                match abi
+                    .functions()
+                    .find(|f| f.selector().0 == name_of_signature_bytes)
+                {
+                    Some(t) => t,
+                    None => {
+                        return Err(QueryBuilderError::FailedToFindFunctionByNameOrSignature(
+                            name_or_signature.to_string(),
+                        ))
+                    }
+                }
+            } else {
+                // If name was passed, overloads are told apart by the selector of the calldata.
+                let functions = match abi.function(name_or_signature) {
+                    Some(functions) if !functions.is_empty() => functions,
+                    _ => {
+                        return Err(QueryBuilderError::FailedToFindFunctionByNameOrSignature(
+                            name_or_signature.to_string(),
+                        ));
                     }
+                };
 
-                    match functions.first() {
-                        Some(t) => t,
-                        None => {
-                            return Err(QueryBuilderError::FailedToFindFunctionByNameOrSignature(
-                                name_or_signature,
-                            ))
-                        }
+                match functions
+                    .iter()
+                    .find(|f| f.selector().0 == calldata_selector)
+                {
+                    Some(t) => t,
+                    None => {
+                        let signatures: Vec<String> =
+                            functions.iter().map(|f| f.full_signature()).collect();
+                        return Err(QueryBuilderError::FunctionDoesNotMatchCalldata {
+                            function: signatures.join(" | "),
+                            calldata_selector: format!("0x{}", hex::encode(calldata_selector)),
+                        });
                     }
                 }
-                None => {
-                    return Err(QueryBuilderError::FailedToFindFunctionByNameOrSignature(
-                        name_or_signature,
-                    ));
-                }
-            }
-        };
+            };
+        check_function_matches_calldata(matched_function, calldata)?;
+        Ok(matched_function.clone())
+    }
 
-        // now that we have a matched function :)
-        // we can create a function builder for it.
-        let mut builder = QueryBuilderForFunction::new(
-            matched_function.clone(),
-            self.tx.clone(),
-            data_field.clone(),
-        );
+    /// Like [`Self::function_builder`], with the function supplied by the caller instead of
+    /// looked up in the ABI of the called contract, so no [`AbiProvider`] is needed.
+    pub fn function_builder_with_abi<T, C>(
+        &mut self,
+        function: T,
+        configurator: C,
+    ) -> Result<&mut Self, QueryBuilderError>
+    where
+        T: IntoFunctionAbi,
+        C: FnOnce(&mut QueryBuilderForFunction) -> Result<(), QueryBuilderError>,
+    {
+        let function = function.into_function_abi()?;
+
+        if self.tx.inner.input().is_empty() {
+            return Err(QueryBuilderError::RequestingFunctionArgumentOfAnEmptyCalldataTransaction);
+        }
+        let calldata = self.tx.inner.input().clone();
+        check_function_matches_calldata(&function, &calldata)?;
+
+        let data_field = self.tx_data_field()?;
+        self.select_from_matched_function(function, calldata, data_field, configurator)?;
+        Ok(self)
+    }
+
+    /// Typed version of [`Self::function_builder_with_abi`] for the `sol!` call `T`, whose
+    /// arguments are picked with [`crate::sol_field!`]. `T` needs `#[sol(abi)]`.
+    pub fn function_builder_typed<T, C>(
+        &mut self,
+        configurator: C,
+    ) -> Result<&mut Self, QueryBuilderError>
+    where
+        T: SolCall + JsonAbiExt<Abi = Function>,
+        C: FnOnce(&mut TypedFunctionBuilder<T>) -> Result<(), QueryBuilderError>,
+    {
+        self.function_builder_with_abi(sol_abi::<T>(), |builder| {
+            configurator(&mut TypedFunctionBuilder::new(builder))
+        })
+    }
+
+    fn tx_data_field(&self) -> Result<FieldMetadata, QueryBuilderError> {
+        match self.mapped_offsets.get(&QueryableFields::TxData) {
+            Some(t) => Ok(t.clone()),
+            None => Err(QueryBuilderError::FailedToFindTxDataField),
+        }
+    }
+
+    fn select_from_matched_function<C>(
+        &mut self,
+        matched_function: Function,
+        calldata: Bytes,
+        data_field: FieldMetadata,
+        configurator: C,
+    ) -> Result<(), QueryBuilderError>
+    where
+        C: FnOnce(&mut QueryBuilderForFunction) -> Result<(), QueryBuilderError>,
+    {
+        let mut builder = QueryBuilderForFunction::new(matched_function, calldata, data_field);
         configurator(&mut builder)?;
         let offsets_from_builder = builder.get_selected_offsets();
         self.selected_offsets.extend(offsets_from_builder);
-        Ok(self)
+        Ok(())
+    }
+
+    /// Falls back to the calldata selector when the contract has no ABI. Candidates that
+    /// can't decode the calldata are ignored, several remaining ones are an error.
+    fn find_function_in_signature_database(
+        &self,
+        name_or_signature: &str,
+        calldata: &[u8],
+    ) -> Result<Function, QueryBuilderError> {
+        let signature_database = match &self.signature_database {
+            Some(sd) => sd,
+            None => return Err(QueryBuilderError::AbiProviderNotInitialized),
+        };
+
+        if calldata.len() < 4 {
+            return Err(QueryBuilderError::DataFieldNotLongEnoughForSignatureExtraction);
+        }
+
+        let mut candidates = signature_database.functions_for_calldata(calldata);
+        if let Some(selector) = function_selector_of(name_or_signature)? {
+            candidates.retain(|f| f.selector().0 == selector);
+        } else {
+            candidates.retain(|f| f.name == name_or_signature);
+        }
+
+        match candidates.as_slice() {
+            [] => Err(QueryBuilderError::FailedToFindFunctionByNameOrSignature(
+                name_or_signature.to_string(),
+            )),
+            [function] => Ok((*function).clone()),
+            _ => Err(QueryBuilderError::AmbiguousSignatureMatch {
+                selector: format!("0x{}", hex::encode(&calldata[..4])),
+                candidates: candidates.iter().map(|f| f.full_signature()).collect(),
+            }),
+        }
+    }
+
+    /// Same as [`Self::find_function_in_signature_database`], for a log of a contract
+    /// without ABI. `Ok(None)` when no entry fits the log.
+    fn find_event_in_signature_database(
+        &self,
+        log: &Log,
+        event_name: Option<&str>,
+    ) -> Result<Option<(DecodedEvent, Event)>, QueryBuilderError> {
+        let signature_database = match &self.signature_database {
+            Some(sd) => sd,
+            None => return Ok(None),
+        };
+
+        let mut candidates = signature_database.events_for_log(&log.inner.data);
+        if let Some(event_name) = event_name {
+            candidates.retain(|e| e.name == event_name);
+        }
+
+        match candidates.as_slice() {
+            [] => Ok(None),
+            [event] => match event.decode_log(&log.inner, true) {
+                Ok(decoded_event) => Ok(Some((decoded_event, (*event).clone()))),
+                Err(_) => Err(QueryBuilderError::FailedToDecodeLog(Box::new(log.clone()))),
+            },
+            _ => Err(QueryBuilderError::AmbiguousSignatureMatch {
+                selector: log.topic0().map(|t| t.to_string()).unwrap_or_default(),
+                candidates: candidates.iter().map(|e| e.full_signature()).collect(),
+            }),
+        }
+    }
+
+    fn can_fall_back_to_signature_database(&self, error: &QueryBuilderError) -> bool {
+        self.signature_database.is_some()
+            && matches!(
+                error,
+                QueryBuilderError::NoAbiFoundForContract(_)
+                    | QueryBuilderError::AbiProviderNotInitialized
+            )
     }
 
     pub fn add_static_field(
@@ -270,12 +733,18 @@ impl QueryBuilder {
         }
     }
 
-    pub async fn multi_event_builder(
+    /// Selects from every log of the event that `filter` keeps, a
+    /// [`LogFilter`](super::log_filter::LogFilter) or a closure, see [`EventFilter`].
+    pub async fn multi_event_builder<F, C>(
         &mut self,
         event_name_or_signature: String,
-        filter: fn(Log, DecodedEvent, usize) -> bool,
-        configurator: fn(&mut QueryBuilderForEvent) -> Result<(), QueryBuilderError>,
-    ) -> Result<&mut Self, QueryBuilderError> {
+        filter: F,
+        configurator: C,
+    ) -> Result<&mut Self, QueryBuilderError>
+    where
+        F: EventFilter + Send,
+        C: Fn(&mut QueryBuilderForEvent) -> Result<(), QueryBuilderError> + Send,
+    {
         let matched_events = self
             .find_all_events(event_name_or_signature.clone(), filter)
             .await?;
@@ -308,14 +777,20 @@ impl QueryBuilder {
         Ok(self)
     }
 
-    pub async fn event_builder(
+    /// Selects from the log of the event that `filter` keeps, a
+    /// [`LogFilter`](super::log_filter::LogFilter) or a closure, see [`EventFilter`].
+    pub async fn event_builder<F, C>(
         &mut self,
         event_name_or_signature: String,
-        filter: fn(Log, DecodedEvent, usize) -> bool,
+        filter: F,
         take_first_if_multiple: bool,
-        configurator: fn(&mut QueryBuilderForEvent) -> Result<(), QueryBuilderError>,
-    ) -> Result<&mut Self, QueryBuilderError> {
-        let (log, decoded_event, log_index, event) = match self
+        configurator: C,
+    ) -> Result<&mut Self, QueryBuilderError>
+    where
+        F: EventFilter + Send,
+        C: FnOnce(&mut QueryBuilderForEvent) -> Result<(), QueryBuilderError> + Send,
+    {
+        let matched_event = match self
             .find_event(
                 event_name_or_signature.clone(),
                 filter,
@@ -331,35 +806,251 @@ impl QueryBuilder {
             }
         };
 
+        self.select_from_matched_event(matched_event, configurator)?;
+        Ok(self)
+    }
+
+    /// Like [`Self::event_builder`], with the event supplied by the caller instead of looked
+    /// up per contract: logs of any contract emitting it match, and no [`AbiProvider`] is
+    /// needed.
+    pub fn event_builder_with_abi<E, F, C>(
+        &mut self,
+        event: E,
+        filter: F,
+        take_first_if_multiple: bool,
+        configurator: C,
+    ) -> Result<&mut Self, QueryBuilderError>
+    where
+        E: IntoEventAbi,
+        F: EventFilter,
+        C: FnOnce(&mut QueryBuilderForEvent) -> Result<(), QueryBuilderError>,
+    {
+        let event = event.into_event_abi()?;
+        if event.anonymous {
+            return Err(QueryBuilderError::AnonymousEventNeedsAddress(
+                event.full_signature(),
+            ));
+        }
+
+        let events = self.find_all_events_with_abi(&event, filter)?;
+        let matched_event = single_event(events, &event, take_first_if_multiple)?;
+        self.select_from_matched_event(matched_event, configurator)?;
+        Ok(self)
+    }
+
+    /// Like [`Self::event_builder_with_abi`], only for logs emitted by `address`. This is
+    /// the way to query `anonymous` events: without a topic 0 to match on, they match logs
+    /// with one topic per indexed argument that decode as `event`.
+    pub fn event_builder_at_address<E, F, C>(
+        &mut self,
+        address: Address,
+        event: E,
+        filter: F,
+        take_first_if_multiple: bool,
+        configurator: C,
+    ) -> Result<&mut Self, QueryBuilderError>
+    where
+        E: IntoEventAbi,
+        F: EventFilter,
+        C: FnOnce(&mut QueryBuilderForEvent) -> Result<(), QueryBuilderError>,
+    {
+        let event = event.into_event_abi()?;
+        let events = self.find_all_events_at_address(address, &event, filter)?;
+        let matched_event = single_event(events, &event, take_first_if_multiple)?;
+        self.select_from_matched_event(matched_event, configurator)?;
+        Ok(self)
+    }
+
+    /// Logs of `address` that decode as `event`, anonymous or not.
+    pub fn find_all_events_at_address<F>(
+        &self,
+        address: Address,
+        event: &Event,
+        filter: F,
+    ) -> Result<Vec<MatchedEvent>, QueryBuilderError>
+    where
+        F: EventFilter,
+    {
+        let mut matches = Vec::new();
+        for (log_index, log) in self.rx.inner.logs().iter().enumerate() {
+            if log.address() != address {
+                continue;
+            }
+
+            let decoded_event = match decode_log_exact(event, &log.inner.data) {
+                Some(decoded_event) => decoded_event,
+                None => continue,
+            };
+            matches.push((log.clone(), decoded_event, log_index, event.clone()));
+        }
+        filter.select(matches)
+    }
+
+    /// Typed version of [`Self::event_builder_with_abi`] for the `sol!` event `T`. Closure
+    /// filters get the decoded event, see [`TypedEventFilter`], and arguments are picked with
+    /// [`crate::sol_field!`]. `T` needs `#[sol(abi)]`.
+    pub fn event_builder_typed<T, F, C>(
+        &mut self,
+        filter: F,
+        take_first_if_multiple: bool,
+        configurator: C,
+    ) -> Result<&mut Self, QueryBuilderError>
+    where
+        T: SolEvent + JsonAbiExt<Abi = Event>,
+        F: TypedEventFilter<T>,
+        C: FnOnce(&mut TypedEventBuilder<T>) -> Result<(), QueryBuilderError>,
+    {
+        self.event_builder_with_abi(
+            sol_abi::<T>(),
+            typed_filter(filter),
+            take_first_if_multiple,
+            |builder| configurator(&mut TypedEventBuilder::new(builder)),
+        )
+    }
+
+    /// Logs of the receipt that decode as `event`, whichever contract emitted them.
+    pub fn find_all_events_with_abi<F>(
+        &self,
+        event: &Event,
+        filter: F,
+    ) -> Result<Vec<MatchedEvent>, QueryBuilderError>
+    where
+        F: EventFilter,
+    {
+        let mut matches = Vec::new();
+        for (log_index, log) in self.rx.inner.logs().iter().enumerate() {
+            if log.topic0() != Some(&event.selector()) {
+                continue;
+            }
+
+            let decoded_event = match decode_log_exact(event, &log.inner.data) {
+                Some(decoded_event) => decoded_event,
+                None => continue,
+            };
+            matches.push((log.clone(), decoded_event, log_index, event.clone()));
+        }
+        filter.select(matches)
+    }
+
+    /// Selects from the log at `log_index` without ABI, through the raw selections of
+    /// [`QueryBuilderForEvent`]: address, topics and data ranges.
+    pub fn raw_log_builder<C>(
+        &mut self,
+        log_index: usize,
+        configurator: C,
+    ) -> Result<&mut Self, QueryBuilderError>
+    where
+        C: FnOnce(&mut QueryBuilderForEvent) -> Result<(), QueryBuilderError>,
+    {
+        let log = match self.rx.inner.logs().get(log_index) {
+            Some(log) => log.clone(),
+            None => return Err(QueryBuilderError::MissingLogInAbiOffsets(log_index)),
+        };
+        let log_field = self.log_field(log_index)?.clone();
+
+        let mut event_builder = QueryBuilderForEvent::new_raw(log_field, log);
+        configurator(&mut event_builder)?;
+        self.selected_offsets
+            .extend(event_builder.get_selected_offsets());
+        Ok(self)
+    }
+
+    /// Selects the length word of the logs array, the number of logs of the receipt.
+    pub fn add_log_count(&mut self) -> Result<&mut Self, QueryBuilderError> {
         let logs_field = match self.mapped_offsets.get(&QueryableFields::RxLogs) {
             Some(lf) => lf,
-            None => {
-                return Err(QueryBuilderError::FailedToFindRxLogsField);
-            }
+            None => return Err(QueryBuilderError::FailedToFindRxLogsField),
+        };
+        self.selected_offsets.push((logs_field.offset, WORD_SIZE));
+        Ok(self)
+    }
+
+    /// Selects topic 0 of every log, in log order. Along with [`Self::add_log_count`] this
+    /// proves an event was not emitted. Logs without topics, only emitted by anonymous
+    /// events, have no topic 0: their topic count, a zero word, is selected instead so that
+    /// every log adds one segment.
+    pub fn add_all_log_signatures(&mut self) -> Result<&mut Self, QueryBuilderError> {
+        let logs_field = match self.mapped_offsets.get(&QueryableFields::RxLogs) {
+            Some(lf) => lf,
+            None => return Err(QueryBuilderError::FailedToFindRxLogsField),
         };
 
-        let log_field = match logs_field.children.get(log_index) {
-            Some(f) => f,
+        let mut signature_offsets = Vec::new();
+        for (log_index, log_field) in logs_field.children.iter().enumerate() {
+            // Children are 0:address, 1:indexed, and 2:data
+            let topics = match log_field.children.get(1) {
+                Some(topics) => topics,
+                None => return Err(QueryBuilderError::MissingLogInAbiOffsets(log_index)),
+            };
+            match topics.children.first() {
+                Some(signature_topic) => {
+                    signature_offsets.push((signature_topic.offset, WORD_SIZE))
+                }
+                // the length word of the topics array.
+                None => signature_offsets.push((topics.offset, WORD_SIZE)),
+            }
+        }
+        self.selected_offsets.extend(signature_offsets);
+        Ok(self)
+    }
+
+    /// Selects `size` bytes of the transaction calldata starting at `start`, without ABI.
+    pub fn add_calldata_range(
+        &mut self,
+        start: usize,
+        size: usize,
+    ) -> Result<&mut Self, QueryBuilderError> {
+        let data_field = self.tx_data_field()?;
+        let offset = check_range(start, size, &data_field)?;
+        self.selected_offsets.push((offset, size));
+        Ok(self)
+    }
+
+    fn log_field(&self, log_index: usize) -> Result<&FieldMetadata, QueryBuilderError> {
+        let logs_field = match self.mapped_offsets.get(&QueryableFields::RxLogs) {
+            Some(lf) => lf,
             None => {
-                return Err(QueryBuilderError::MissingLogInAbiOffsets(log_index));
+                return Err(QueryBuilderError::FailedToFindRxLogsField);
             }
         };
 
+        match logs_field.children.get(log_index) {
+            Some(f) => Ok(f),
+            None => Err(QueryBuilderError::MissingLogInAbiOffsets(log_index)),
+        }
+    }
+
+    /// Runs the configurator against an event previously returned by [`Self::find_all_events`]
+    /// and records the offsets it selected.
+    pub(crate) fn select_from_matched_event<C>(
+        &mut self,
+        matched_event: (Log, DecodedEvent, usize, Event),
+        configurator: C,
+    ) -> Result<(), QueryBuilderError>
+    where
+        C: FnOnce(&mut QueryBuilderForEvent) -> Result<(), QueryBuilderError>,
+    {
+        let (log, decoded_event, log_index, event) = matched_event;
+        let log_field = self.log_field(log_index)?;
+
         let mut event_builder =
             QueryBuilderForEvent::new(log_field.clone(), log, decoded_event, event);
         configurator(&mut event_builder)?;
         let selected_offsets_from_event_builder = event_builder.get_selected_offsets();
         self.selected_offsets
             .extend(selected_offsets_from_event_builder);
-        Ok(self)
+        Ok(())
     }
 
-    pub async fn find_event(
+    pub async fn find_event<F>(
         &mut self,
         event_name_or_signature: String,
-        filter: fn(Log, DecodedEvent, usize) -> bool,
+        filter: F,
         take_first_if_multiple: bool,
-    ) -> Result<Option<(Log, DecodedEvent, usize, Event)>, QueryBuilderError> {
+    ) -> Result<Option<MatchedEvent>, QueryBuilderError>
+    where
+        F: EventFilter + Send,
+    {
         let events = self
             .find_all_events(event_name_or_signature.clone(), filter)
             .await?;
@@ -375,103 +1066,127 @@ impl QueryBuilder {
         }
     }
 
-    pub async fn find_all_events(
+    /// Finds the logs of an event by name, `0x` topic or signature. In
+    /// [`EventSearchMode::Tolerant`] logs without ABI or that fail to decode are skipped and
+    /// recorded in [`Self::get_warnings`] instead of failing the search.
+    pub async fn find_all_events<F>(
         &mut self,
         event_name_or_signature: String,
-        filter: fn(Log, DecodedEvent, usize) -> bool,
-    ) -> Result<Vec<(Log, DecodedEvent, usize, Event)>, QueryBuilderError> {
+        filter: F,
+    ) -> Result<Vec<MatchedEvent>, QueryBuilderError>
+    where
+        F: EventFilter + Send,
+    {
         let mut extended_logs = Vec::new();
+        let mut warnings = Vec::new();
+        let mut skip_or_fail =
+            |log_index: usize, log: &Log, error: QueryBuilderError| match self.event_search_mode {
+                EventSearchMode::Strict => Err(error),
+                EventSearchMode::Tolerant => {
+                    warnings.push(QueryWarning::SkippedLog {
+                        log_index,
+                        address: log.address(),
+                        error,
+                    });
+                    Ok(())
+                }
+            };
 
-        if event_name_or_signature.starts_with("0x") {
-            let event_signature_as_fixed_bytes =
-                match FixedBytes::<32>::from_hex(event_name_or_signature.clone()) {
-                    Ok(decoded_fixed_bytes) => decoded_fixed_bytes,
-                    Err(_) => {
-                        return Err(QueryBuilderError::EventSignatureNameProvidedIsNotValidHex)
-                    }
-                };
-
+        if let Some(event_signature_as_fixed_bytes) = event_selector_of(&event_name_or_signature)? {
             // filter the logs, that match the criteria.
             let mut filtered_logs = Vec::new();
             let mut contract_addresses = Vec::new();
             for (log_index, log) in self.rx.inner.logs().iter().enumerate() {
                 if let Some(event_hash) = log.topic0() {
                     if event_hash.eq(&event_signature_as_fixed_bytes) {
-                        contract_addresses.push(log.address().to_string());
+                        contract_addresses.push(log.address());
                         filtered_logs.push((log_index, log.clone()));
                     }
                 }
             }
 
             // get the contract addresses
-            let abis = self
-                .get_abis_of_contract_addresses(contract_addresses)
-                .await?;
+            let (abis, abi_failures) = self.get_abis_for_logs(contract_addresses).await?;
             for (log_index, log) in filtered_logs {
-                let contract_address = log.address().to_string();
-                let abi = match abis.get(&contract_address) {
-                    Some(a) => a,
-                    None => {
-                        return Err(QueryBuilderError::NoAbiFoundForContract(
-                            contract_address.clone(),
-                        ))
+                let decoded = match abis.get(&log.address()) {
+                    Some(abi) => {
+                        let event_of_signature = abi
+                            .events()
+                            .find(|e| e.selector().0 == event_signature_as_fixed_bytes.0);
+
+                        match event_of_signature {
+                            // we have the event woot woot.
+                            Some(event) => match event.decode_log(&log.inner, true) {
+                                Ok(decoded_event) => Ok((decoded_event, event.clone())),
+                                Err(_) => {
+                                    Err(QueryBuilderError::FailedToDecodeLog(Box::new(log.clone())))
+                                }
+                            },
+                            None => Err(QueryBuilderError::FailedToFindEventByNameOrSignature(
+                                event_name_or_signature.clone(),
+                            )),
+                        }
+                    }
+                    None if self.signature_database.is_some() => {
+                        match self.find_event_in_signature_database(&log, None) {
+                            Ok(Some(found)) => Ok(found),
+                            Ok(None) => Err(QueryBuilderError::FailedToFindEventByNameOrSignature(
+                                event_name_or_signature.clone(),
+                            )),
+                            Err(e) => Err(e),
+                        }
                     }
+                    None => Err(missing_abi(&abi_failures, log.address())),
                 };
 
-                let event_of_signature = abi
-                    .events()
-                    .find(|e| e.selector().0 == event_signature_as_fixed_bytes.0);
-
-                if let Some(event) = event_of_signature {
-                    // we have the event woot woot.
-                    match event.decode_log(&log.inner, true) {
-                        Ok(decoded_event) => {
-                            extended_logs.push((log, decoded_event, log_index, event.clone()));
-                        }
-                        Err(_) => {
-                            return Err(QueryBuilderError::FailedToDecodeLog(Box::new(
-                                log.clone(),
-                            )));
-                        }
+                match decoded {
+                    Ok((decoded_event, event)) => {
+                        extended_logs.push((log, decoded_event, log_index, event));
                     }
-                } else {
-                    return Err(QueryBuilderError::FailedToFindEventByNameOrSignature(
-                        event_name_or_signature.clone(),
-                    ));
+                    Err(e) => skip_or_fail(log_index, &log, e)?,
                 }
             }
         } else {
             // we need to get all the abi's possible in the events.
-            let abis = self.get_receipt_abis().await?;
-            let mut log_index = 0;
-            for log in self.rx.inner.logs() {
+            let contract_addresses = self.rx.inner.logs().iter().map(|f| f.address()).collect();
+            let (abis, abi_failures) = self.get_abis_for_logs(contract_addresses).await?;
+            for (log_index, log) in self.rx.inner.logs().iter().enumerate() {
                 // get the ABI for this log.
-                let abi = match abis.get(&log.address().to_string()) {
+                let abi = match abis.get(&log.address()) {
                     Some(json_abi) => json_abi,
+                    // logs the database doesn't know of can't be the event we look for.
+                    None if self.signature_database.is_some() => {
+                        match self
+                            .find_event_in_signature_database(log, Some(&event_name_or_signature))
+                        {
+                            Ok(Some((decoded_event, event))) => {
+                                extended_logs.push((log.clone(), decoded_event, log_index, event));
+                            }
+                            Ok(None) => {}
+                            Err(e) => skip_or_fail(log_index, log, e)?,
+                        }
+                        continue;
+                    }
                     None => {
-                        return Err(QueryBuilderError::NoAbiFoundForContract(
-                            log.address().to_string(),
-                        ));
+                        skip_or_fail(log_index, log, missing_abi(&abi_failures, log.address()))?;
+                        continue;
                     }
                 };
 
                 // get the event signature
                 let event_signature = match log.topic0() {
                     Some(es) => es,
-                    None => {
-                        log_index += 1;
-                        continue;
-                    }
+                    None => continue,
                 };
 
                 // find the event...
                 let event_of_signature = abi.events().find(|e| e.selector().0 == event_signature.0);
 
                 // attempt to parse the log :)
-                if let Some(event) = event_of_signature {
+                match event_of_signature {
                     // before we try to decode the log, lets first check its the event name
                     // we care about..
-                    if event.name == event_name_or_signature {
+                    Some(event) if event.name == event_name_or_signature => {
                         // we have the event woot woot.
                         match event.decode_log(&log.inner, true) {
                             Ok(decoded_event) => {
@@ -482,104 +1197,135 @@ impl QueryBuilder {
                                     event.clone(),
                                 ));
                             }
-                            Err(_) => {
-                                return Err(QueryBuilderError::FailedToDecodeLog(Box::new(
-                                    log.clone(),
-                                )));
-                            }
+                            Err(_) => skip_or_fail(
+                                log_index,
+                                log,
+                                QueryBuilderError::FailedToDecodeLog(Box::new(log.clone())),
+                            )?,
                         }
                     }
-                } else {
+                    Some(_) => {}
                     // its not that we can't find it, its more that the log is not decodable
                     // due not being able to find the event in the ABI.
-                    return Err(QueryBuilderError::FailedToDecodeLog(Box::new(log.clone())));
+                    None => skip_or_fail(
+                        log_index,
+                        log,
+                        QueryBuilderError::FailedToDecodeLog(Box::new(log.clone())),
+                    )?,
                 }
-
-                log_index += 1;
             }
         }
+        self.warnings.extend(warnings);
 
         // now that we have only extended logs of an event that either matches by name or signature.
         // we just need to offer the ability to filter to the user..
-        let mut matches = Vec::new();
-        for (log, decoded_event, log_index, event) in extended_logs {
-            if filter(log.clone(), decoded_event.clone(), log_index) {
-                matches.push((log, decoded_event, log_index, event));
-            }
-        }
-
-        Ok(matches)
+        filter.select(extended_logs)
     }
 
-    pub async fn get_receipt_abis(
-        &mut self,
-    ) -> Result<HashMap<String, JsonAbi>, QueryBuilderError> {
-        let contract_addresses: Vec<String> = self
-            .rx
-            .inner
-            .logs()
-            .iter()
-            .map(|f| f.address().to_string())
-            .collect();
-
-        let result = self
-            .get_abis_of_contract_addresses(contract_addresses)
-            .await?;
-        Ok(result)
+    pub async fn get_receipt_abis(&mut self) -> Result<AbiBatch, QueryBuilderError> {
+        let contract_addresses: Vec<Address> =
+            self.rx.inner.logs().iter().map(|f| f.address()).collect();
+
+        self.get_abis_of_contract_addresses(contract_addresses)
+            .await
     }
 
+    /// Resolves every address, returning the ABIs found next to the failure of each
+    /// address that has none. Only fails without an ABI provider.
     pub async fn get_abis_of_contract_addresses(
         &mut self,
-        contract_addresses: Vec<String>,
-    ) -> Result<HashMap<String, JsonAbi>, QueryBuilderError> {
-        let unique_contract_addresses: HashSet<_> = contract_addresses.into_iter().collect();
-        let mut abi_map = HashMap::new();
-        for contract_address in unique_contract_addresses {
-            let abi: JsonAbi = self
-                .get_abi_from_provider_cached(contract_address.clone())
-                .await?;
-            abi_map.insert(contract_address.clone(), abi);
+        contract_addresses: Vec<Address>,
+    ) -> Result<AbiBatch, QueryBuilderError> {
+        let abi_registry = match &self.abi_registry {
+            Some(ar) => ar,
+            None => return Err(QueryBuilderError::AbiProviderNotInitialized),
+        };
+
+        let contexts = contract_addresses
+            .into_iter()
+            .map(|address| self.abi_request_context(address));
+        Ok(abi_registry.get_abis(contexts).await)
+    }
+
+    /// Like [`Self::get_abis_of_contract_addresses`], but with a signature database set,
+    /// contracts without ABI are left out instead of failing the lookup. In
+    /// [`EventSearchMode::Tolerant`] the other failures are returned next to the ABIs.
+    async fn get_abis_for_logs(
+        &self,
+        contract_addresses: Vec<Address>,
+    ) -> Result<AbisAndFailures, QueryBuilderError> {
+        let abi_registry = match (&self.abi_registry, &self.signature_database) {
+            (Some(ar), _) => ar,
+            (None, Some(_)) => return Ok(Default::default()),
+            (None, None) => return Err(QueryBuilderError::AbiProviderNotInitialized),
+        };
+
+        let contexts = contract_addresses
+            .into_iter()
+            .map(|address| self.abi_request_context(address));
+        let mut batch = abi_registry.get_abis(contexts).await;
+        batch
+            .failures
+            .retain(|(_, e)| !self.can_fall_back_to_signature_database(e));
+        if self.event_search_mode == EventSearchMode::Tolerant {
+            return Ok((batch.abis, batch.failures.into_iter().collect()));
+        }
+        match batch.failures.len() {
+            0 => Ok((batch.abis, HashMap::new())),
+            1 => Err(batch.failures.remove(0).1),
+            _ => Err(QueryBuilderError::FailedToRetrieveAbis(batch.failures)),
         }
-        Ok(abi_map)
     }
 
     pub async fn get_abi_from_provider_cached(
         &mut self,
-        contract_address: String,
+        contract_address: Address,
     ) -> Result<JsonAbi, QueryBuilderError> {
-        let result = match self.abi_cache.get(&contract_address.clone()) {
-            Some(existing) => existing,
-            None => {
-                let abi = self.get_abi_from_provider(contract_address.clone()).await?;
-                self.abi_cache.insert(contract_address.clone(), abi.clone());
-                &abi.clone()
-            }
+        let abi_registry = match &self.abi_registry {
+            Some(ar) => ar,
+            None => return Err(QueryBuilderError::AbiProviderNotInitialized),
         };
 
-        Ok(result.clone())
+        abi_registry
+            .get_abi(&self.abi_request_context(contract_address))
+            .await
     }
 
     pub async fn get_abi_from_provider(
         &self,
-        contract_address: String,
+        contract_address: Address,
     ) -> Result<JsonAbi, QueryBuilderError> {
-        let abi_provider = match &self.abi_provider {
-            Some(ap) => ap,
+        let abi_registry = match &self.abi_registry {
+            Some(ar) => ar,
             None => return Err(QueryBuilderError::AbiProviderNotInitialized),
         };
 
-        let abi_raw = abi_provider.get_abi(contract_address.clone()).await?;
+        abi_registry
+            .fetch_abi(&self.abi_request_context(contract_address))
+            .await
+    }
 
-        match JsonAbi::from_json_str(&abi_raw) {
-            Ok(json_abi) => Ok(json_abi),
-            Err(_) => Err(QueryBuilderError::FailedToParseAbi(
-                contract_address.clone(),
-                abi_raw,
-            )),
-        }
+    /// Everything skipped so far by tolerant event searches.
+    pub fn get_warnings(&self) -> &[QueryWarning] {
+        &self.warnings
     }
 
     pub fn get_selected_offsets(&self) -> Vec<(usize, usize)> {
         self.selected_offsets.clone()
     }
+
+    pub(crate) fn selected_offsets_count(&self) -> usize {
+        self.selected_offsets.len()
+    }
+
+    /// Drops the offsets selected after the first `count`.
+    pub(crate) fn truncate_selected_offsets(&mut self, count: usize) {
+        self.selected_offsets.truncate(count);
+    }
+
+    /// Same selection as [`Self::get_selected_offsets`], but coalesced into the smallest
+    /// set of sorted segments. Useful when the prover charges per segment.
+    pub fn get_normalized_offsets(&self) -> NormalizedSegments {
+        normalize_segments(&self.selected_offsets)
+    }
 }
@@ -0,0 +1,77 @@
+use alloy::{dyn_abi::Specifier, json_abi::Constructor, primitives::Bytes};
+
+use crate::abi::utils::compute_abi_offsets;
+
+use super::models::QueryBuilderError;
+
+/// Selects constructor arguments of a contract creation, abi-encoded right after the
+/// creation bytecode in the calldata.
+pub struct QueryBuilderForConstructor {
+    selected_offsets: Vec<(usize, usize)>,
+    constructor: Constructor,
+    arguments: Bytes,
+    arguments_offset: usize,
+}
+
+impl QueryBuilderForConstructor {
+    pub(crate) fn new(constructor: Constructor, arguments: Bytes, arguments_offset: usize) -> Self {
+        Self {
+            selected_offsets: vec![],
+            constructor,
+            arguments,
+            arguments_offset,
+        }
+    }
+
+    pub fn get_selected_offsets(self) -> Vec<(usize, usize)> {
+        self.selected_offsets.clone()
+    }
+
+    pub fn constructor(&self) -> &Constructor {
+        &self.constructor
+    }
+
+    pub fn add_argument(&mut self, name: String) -> Result<&mut Self, QueryBuilderError> {
+        let argument_index = self
+            .constructor
+            .inputs
+            .iter()
+            .position(|argument| argument.name().eq(&name));
+
+        match argument_index {
+            Some(argument_index) => self.add_argument_at(argument_index),
+            None => Err(QueryBuilderError::CannotFindArgumentInConstructor(name)),
+        }
+    }
+
+    /// Selects the argument at `argument_index`, e.g. for unnamed parameters.
+    pub fn add_argument_at(
+        &mut self,
+        argument_index: usize,
+    ) -> Result<&mut Self, QueryBuilderError> {
+        let mut constructor_sol_types = Vec::new();
+        for input in &self.constructor.inputs {
+            match input.resolve() {
+                Ok(st) => constructor_sol_types.push(st),
+                Err(_) => return Err(QueryBuilderError::FailedToResolveSolTypesOfConstructor),
+            }
+        }
+
+        let computed_offsets = match compute_abi_offsets(constructor_sol_types, &self.arguments) {
+            Ok(offsets) => offsets,
+            Err(_) => return Err(QueryBuilderError::FailedToComputeOffsetsForCalldata),
+        };
+
+        match computed_offsets.get(argument_index) {
+            Some(field) => match field.size {
+                Some(size) => {
+                    self.selected_offsets
+                        .push((self.arguments_offset + field.offset, size));
+                    Ok(self)
+                }
+                None => Err(QueryBuilderError::TryingToGetSizeOfDynamicType),
+            },
+            None => Err(QueryBuilderError::MissingDataInCalldataOffsets),
+        }
+    }
+}
@@ -6,14 +6,15 @@ use alloy_json_abi::Event;
 
 use super::{
     models::{FieldMetadata, QueryBuilderError},
-    utils::compute_abi_offsets,
+    utils::{compute_abi_offsets, WORD_SIZE},
 };
 
 pub struct QueryBuilderForEvent {
     field: FieldMetadata,
     log: Log,
-    _decoded_event: DecodedEvent,
-    event: Event,
+    _decoded_event: Option<DecodedEvent>,
+    /// `None` for raw logs, selected by index without ABI.
+    event: Option<Event>,
     selected_offsets: Vec<(usize, usize)>,
 }
 
@@ -27,113 +28,124 @@ impl QueryBuilderForEvent {
         Self {
             field: log_field,
             log,
-            _decoded_event,
-            event,
+            _decoded_event: Some(_decoded_event),
+            event: Some(event),
             selected_offsets: vec![],
         }
     }
 
+    pub(crate) fn new_raw(log_field: FieldMetadata, log: Log) -> Self {
+        Self {
+            field: log_field,
+            log,
+            _decoded_event: None,
+            event: None,
+            selected_offsets: vec![],
+        }
+    }
+
+    fn matched_event(&self) -> Result<&Event, QueryBuilderError> {
+        match &self.event {
+            Some(event) => Ok(event),
+            None => Err(QueryBuilderError::RawLogHasNoEvent),
+        }
+    }
+
     pub fn add_argument(&mut self, name: &str) -> Result<&mut Self, QueryBuilderError> {
-        let mut topic_index: usize = 0;
-        let mut data_index: usize = 0;
-
-        for event_input in self.event.inputs.clone() {
-            // We add 1 before loop contents to skip index 0, which always holds the
-            // event signature.
-            if event_input.indexed {
-                topic_index += 1;
+        match self
+            .matched_event()?
+            .inputs
+            .iter()
+            .position(|input| input.name == name)
+        {
+            Some(argument_index) => self.add_argument_at(argument_index),
+            None => Err(QueryBuilderError::MissingDataInAbiOffsets),
+        }
+    }
+
+    /// Selects the argument at `argument_index` in the event declaration, whether it is
+    /// indexed or not.
+    pub fn add_argument_at(
+        &mut self,
+        argument_index: usize,
+    ) -> Result<&mut Self, QueryBuilderError> {
+        let event = self.matched_event()?.clone();
+        let event_input = match event.inputs.get(argument_index) {
+            Some(ei) => ei,
+            None => return Err(QueryBuilderError::MissingDataInAbiOffsets),
+        };
+        let preceding_inputs = &event.inputs[..argument_index];
+
+        if event_input.indexed {
+            // topic 0 holds the event signature, unless the event is anonymous.
+            let first_argument_topic = if event.anonymous { 0 } else { 1 };
+            let topic_index =
+                first_argument_topic + preceding_inputs.iter().filter(|i| i.indexed).count();
+
+            // Children are 0:address, 1:indexed, and 2:data
+            let topics = match self.field.children.get(1) {
+                Some(topics) => topics,
+                None => return Err(QueryBuilderError::MissingDataInAbiOffsets),
+            };
+            match topics.children.get(topic_index) {
+                Some(subject_topic) => {
+                    // all topics are 32 length :)
+                    self.selected_offsets.push((subject_topic.offset, 32));
+                    Ok(self)
+                }
+                None => Err(QueryBuilderError::MissingDataInAbiOffsets),
             }
+        } else {
+            // its a data field.
+            let data_index = preceding_inputs.iter().filter(|i| !i.indexed).count();
+            let data_field = match self.field.children.get(2) {
+                Some(df) => df,
+                None => {
+                    return Err(QueryBuilderError::MissingDataInAbiOffsets);
+                }
+            };
 
-            if event_input.name == name {
-                if event_input.indexed {
-                    // if its indexed..
-                    // calculate the offset..
-                    match self.field.children.get(1) {
-                        // Children are 0:address, 1:indexed, and 2:data
-                        Some(topics) => {
-                            match topics.children.get(topic_index) {
-                                Some(subject_topic) => {
-                                    // all topics are 32 length :)
-                                    // if you want to be extra safe you can always also do
-                                    // match subject_topic.size
-                                    self.selected_offsets.push((subject_topic.offset, 32));
-                                    return Ok(self);
-                                }
-                                None => {
-                                    return Err(QueryBuilderError::MissingDataInAbiOffsets);
-                                }
-                            }
-                        }
-                        None => {
-                            return Err(QueryBuilderError::MissingDataInAbiOffsets);
-                        }
+            // construct a list of body solidity types.
+            let mut body_sol_types = Vec::new();
+            for input in event.inputs.iter().filter(|i| !i.indexed) {
+                match input.resolve() {
+                    Ok(st) => {
+                        body_sol_types.push(st);
                     }
-                } else {
-                    // its a data field.
-                    let data_field = match self.field.children.get(2) {
-                        Some(df) => df,
-                        None => {
-                            return Err(QueryBuilderError::MissingDataInAbiOffsets);
-                        }
-                    };
-
-                    // construct a list of body solidity types.
-                    let mut body_sol_types = Vec::new();
-                    for input in self.event.inputs.clone() {
-                        if !input.indexed {
-                            match input.resolve() {
-                                Ok(st) => {
-                                    body_sol_types.push(st);
-                                }
-                                Err(_) => {
-                                    return Err(
-                                        QueryBuilderError::FailedToResolveSolTypesOfMatchedEvent(
-                                            self.event.clone(),
-                                        ),
-                                    );
-                                }
-                            }
-                        }
-                    }
-
-                    // we have the body solidity types :) of the data field.
-                    // we need to compute its offsets, similar to our transaction.
-                    let event_data_offsets =
-                        match compute_abi_offsets(body_sol_types, &self.log.data().data) {
-                            Ok(offsets) => offsets,
-                            Err(_) => {
-                                return Err(QueryBuilderError::FailedToGetEventDataOffsets(
-                                    Box::new(self.log.clone()),
-                                ))
-                            }
-                        };
-
-                    match event_data_offsets.get(data_index) {
-                        Some(argument_field) => match argument_field.size {
-                            Some(argument_field_size) => {
-                                self.selected_offsets.push((
-                                    data_field.offset + argument_field.offset,
-                                    argument_field_size,
-                                ));
-                                return Ok(self);
-                            }
-                            None => {
-                                return Err(QueryBuilderError::TryingToGetSizeOfDynamicType);
-                            }
-                        },
-                        None => {
-                            return Err(QueryBuilderError::MissingDataInAbiOffsets);
-                        }
+                    Err(_) => {
+                        return Err(QueryBuilderError::FailedToResolveSolTypesOfMatchedEvent(
+                            event.clone(),
+                        ));
                     }
                 }
             }
 
-            if !event_input.indexed {
-                data_index += 1;
+            // we have the body solidity types :) of the data field.
+            // we need to compute its offsets, similar to our transaction.
+            let event_data_offsets =
+                match compute_abi_offsets(body_sol_types, &self.log.data().data) {
+                    Ok(offsets) => offsets,
+                    Err(_) => {
+                        return Err(QueryBuilderError::FailedToGetEventDataOffsets(Box::new(
+                            self.log.clone(),
+                        )))
+                    }
+                };
+
+            match event_data_offsets.get(data_index) {
+                Some(argument_field) => match argument_field.size {
+                    Some(argument_field_size) => {
+                        self.selected_offsets.push((
+                            data_field.offset + argument_field.offset,
+                            argument_field_size,
+                        ));
+                        Ok(self)
+                    }
+                    None => Err(QueryBuilderError::TryingToGetSizeOfDynamicType),
+                },
+                None => Err(QueryBuilderError::MissingDataInAbiOffsets),
             }
         }
-
-        Err(QueryBuilderError::MissingDataInAbiOffsets)
     }
 
     pub fn add_address(&mut self) -> Result<&mut Self, QueryBuilderError> {
@@ -151,6 +163,13 @@ impl QueryBuilderForEvent {
     }
 
     pub fn add_signature(&mut self) -> Result<&mut Self, QueryBuilderError> {
+        let event = self.matched_event()?;
+        if event.anonymous {
+            return Err(QueryBuilderError::AnonymousEventHasNoSignature(
+                event.full_signature(),
+            ));
+        }
+
         // this is the topics..
         match self.field.children.get(1) {
             Some(topics) => match topics.children.first() {
@@ -171,4 +190,83 @@ impl QueryBuilderForEvent {
     pub fn get_selected_offsets(self) -> Vec<(usize, usize)> {
         self.selected_offsets.clone()
     }
+
+    /// Selects topic `topic_index` of the log as is, topic 0 being the signature of
+    /// non-anonymous events.
+    pub fn add_topic(&mut self, topic_index: usize) -> Result<&mut Self, QueryBuilderError> {
+        let topics = match self.field.children.get(1) {
+            Some(topics) => topics,
+            None => return Err(QueryBuilderError::MissingDataInAbiOffsets),
+        };
+        match topics.children.get(topic_index) {
+            Some(topic) => {
+                self.selected_offsets.push((topic.offset, 32));
+                Ok(self)
+            }
+            None => Err(QueryBuilderError::TopicIndexOutOfBounds {
+                topic_index,
+                topic_count: topics.children.len(),
+            }),
+        }
+    }
+
+    /// Selects `size` bytes of the log data starting at `start`, without decoding it.
+    pub fn add_data_range(
+        &mut self,
+        start: usize,
+        size: usize,
+    ) -> Result<&mut Self, QueryBuilderError> {
+        let data_field = match self.field.children.get(2) {
+            Some(df) => df,
+            None => return Err(QueryBuilderError::MissingDataInAbiOffsets),
+        };
+        let offset = check_range(start, size, data_field)?;
+        self.selected_offsets.push((offset, size));
+        Ok(self)
+    }
+
+    /// Selects the whole log as a single segment, which decodes on its own with
+    /// `abi.decode(segment, (address, bytes32[], bytes))`.
+    pub fn add_log(&mut self) -> Result<&mut Self, QueryBuilderError> {
+        let data_field = match self.field.children.get(2) {
+            Some(df) => df,
+            None => return Err(QueryBuilderError::MissingDataInAbiOffsets),
+        };
+        let data_size = match data_field.size {
+            Some(s) => s,
+            None => return Err(QueryBuilderError::TryingToGetSizeOfDynamicType),
+        };
+
+        // the data is the last member of the tuple, padded to a whole number of words.
+        let end = data_field.offset + data_size.div_ceil(WORD_SIZE) * WORD_SIZE;
+        self.selected_offsets
+            .push((self.field.offset, end - self.field.offset));
+        Ok(self)
+    }
+
+    /// `None` for logs selected with
+    /// [`super::query_builder::QueryBuilder::raw_log_builder`].
+    pub fn event(&self) -> Option<&Event> {
+        self.event.as_ref()
+    }
+}
+
+/// Absolute offset of `size` bytes at `start` within the dynamic `field`.
+pub(crate) fn check_range(
+    start: usize,
+    size: usize,
+    field: &FieldMetadata,
+) -> Result<usize, QueryBuilderError> {
+    let field_size = match field.size {
+        Some(s) => s,
+        None => return Err(QueryBuilderError::TryingToGetSizeOfDynamicType),
+    };
+    match start.checked_add(size) {
+        Some(end) if size > 0 && end <= field_size => Ok(field.offset + start),
+        _ => Err(QueryBuilderError::RangeOutOfBounds {
+            start,
+            size,
+            field_size,
+        }),
+    }
 }
@@ -1,30 +1,40 @@
-use alloy::{consensus::Transaction as _, dyn_abi::Specifier, rpc::types::Transaction};
+use alloy::{
+    dyn_abi::{DynSolType, Specifier},
+    primitives::{Address, Bytes},
+};
 use alloy_json_abi::Function;
 
 use crate::abi::utils::compute_abi_offsets;
 
-use super::models::{FieldMetadata, QueryBuilderError};
+use super::{
+    inline_abi::{params_abi, IntoFunctionAbi},
+    models::{FieldMetadata, NestedCalldata, QueryBuilderError},
+    query_builder_for_event::check_range,
+};
 
 const FUNCTION_SIGNATURE_SIZE: usize = 4;
 
 pub struct QueryBuilderForFunction {
     selected_offsets: Vec<(usize, usize)>,
     matched_function: Function,
-    tx: Transaction,
+    calldata: Bytes,
     data_field: FieldMetadata,
+    // 0 for nested payloads holding only encoded parameters.
+    selector_size: usize,
 }
 
 impl QueryBuilderForFunction {
     pub(crate) fn new(
         matched_function: Function,
-        tx: Transaction,
+        calldata: Bytes,
         data_field: FieldMetadata,
     ) -> Self {
         Self {
             selected_offsets: vec![],
             matched_function,
-            tx,
+            calldata,
             data_field,
+            selector_size: FUNCTION_SIGNATURE_SIZE,
         }
     }
 
@@ -32,7 +42,14 @@ impl QueryBuilderForFunction {
         self.selected_offsets.clone()
     }
 
+    pub fn function(&self) -> &Function {
+        &self.matched_function
+    }
+
     pub fn add_signature(&mut self) -> Result<&mut Self, QueryBuilderError> {
+        if self.selector_size == 0 {
+            return Err(QueryBuilderError::NestedParamsHaveNoSelector);
+        }
         if let Some(size) = self.data_field.size {
             if size >= FUNCTION_SIGNATURE_SIZE {
                 self.selected_offsets
@@ -47,29 +64,189 @@ impl QueryBuilderForFunction {
     }
 
     pub fn add_argument(&mut self, name: String) -> Result<&mut Self, QueryBuilderError> {
-        let mut found_argument_index: Option<usize> = None;
-        for (argument_index, argument) in self.matched_function.inputs.iter().enumerate() {
-            if argument.name().eq(&name) {
-                found_argument_index = Some(argument_index);
-                break;
+        let argument_index = self.argument_index(&name)?;
+        self.add_argument_at(argument_index)
+    }
+
+    /// Selects the argument at `argument_index`, e.g. for unnamed parameters.
+    pub fn add_argument_at(
+        &mut self,
+        argument_index: usize,
+    ) -> Result<&mut Self, QueryBuilderError> {
+        let field = self.argument_field(argument_index)?;
+        self.add_field(&field)
+    }
+
+    /// Selects `size` bytes of the calldata starting at `start`, selector included, without
+    /// decoding it.
+    pub fn add_calldata_range(
+        &mut self,
+        start: usize,
+        size: usize,
+    ) -> Result<&mut Self, QueryBuilderError> {
+        let offset = check_range(start, size, &self.data_field)?;
+        self.selected_offsets.push((offset, size));
+        Ok(self)
+    }
+
+    /// Selects a field of the arguments, e.g. a component of a struct argument.
+    pub(crate) fn add_field(
+        &mut self,
+        field: &FieldMetadata,
+    ) -> Result<&mut Self, QueryBuilderError> {
+        match field.size {
+            Some(size) => {
+                self.selected_offsets.push((
+                    self.data_field.offset + self.selector_size + field.offset,
+                    size,
+                ));
+                Ok(self)
+            }
+            None => Err(QueryBuilderError::TryingToGetSizeOfDynamicType),
+        }
+    }
+
+    /// Opens a builder on the calldata of an inner call carried by a `bytes` argument, with
+    /// the inner function supplied by the caller. Selections made on the inner builder are
+    /// absolute offsets in the encoding, like the ones of this builder.
+    pub fn nested_function_builder_with_abi<T, C>(
+        &mut self,
+        calldata: &NestedCalldata,
+        function: T,
+        configurator: C,
+    ) -> Result<&mut Self, QueryBuilderError>
+    where
+        T: IntoFunctionAbi,
+        C: FnOnce(&mut QueryBuilderForFunction) -> Result<(), QueryBuilderError>,
+    {
+        let field = self.nested_calldata_field(calldata)?;
+        self.nested_function_builder_at(field, function, configurator)
+    }
+
+    /// Opens a builder on a `bytes` argument holding only abi-encoded parameters, without
+    /// selector, such as the `inputs` of a Universal Router `execute(bytes commands,
+    /// bytes[] inputs)`. `params` lists their types as in a function signature, e.g.
+    /// `"address recipient, uint256 amountIn, uint256 amountOutMin, bytes path, bool
+    /// payerIsUser"`, and the inner builder sees them as a function named `params`.
+    pub fn nested_params_builder<C>(
+        &mut self,
+        calldata: &NestedCalldata,
+        params: &str,
+        configurator: C,
+    ) -> Result<&mut Self, QueryBuilderError>
+    where
+        C: FnOnce(&mut QueryBuilderForFunction) -> Result<(), QueryBuilderError>,
+    {
+        let function = params_abi(params)?;
+        let field = self.nested_calldata_field(calldata)?;
+        let (inner_calldata, inner_data_field) = self.inner_calldata(field)?;
+
+        let mut builder = QueryBuilderForFunction::new(function, inner_calldata, inner_data_field);
+        builder.selector_size = 0;
+        configurator(&mut builder)?;
+        self.selected_offsets.extend(builder.selected_offsets);
+        Ok(self)
+    }
+
+    /// [`Self::nested_function_builder_with_abi`] on a `bytes` field of the arguments.
+    pub(crate) fn nested_function_builder_at<T, C>(
+        &mut self,
+        field: FieldMetadata,
+        function: T,
+        configurator: C,
+    ) -> Result<&mut Self, QueryBuilderError>
+    where
+        T: IntoFunctionAbi,
+        C: FnOnce(&mut QueryBuilderForFunction) -> Result<(), QueryBuilderError>,
+    {
+        let function = function.into_function_abi()?;
+        let (inner_calldata, inner_data_field) = self.inner_calldata(field)?;
+        check_function_matches_calldata(&function, &inner_calldata)?;
+
+        let mut builder = QueryBuilderForFunction::new(function, inner_calldata, inner_data_field);
+        configurator(&mut builder)?;
+        self.selected_offsets.extend(builder.selected_offsets);
+        Ok(self)
+    }
+
+    /// The inner calldata and where it sits in the encoding.
+    pub(crate) fn nested_calldata(
+        &self,
+        calldata: &NestedCalldata,
+    ) -> Result<(Bytes, FieldMetadata), QueryBuilderError> {
+        let field = self.nested_calldata_field(calldata)?;
+        self.inner_calldata(field)
+    }
+
+    fn nested_calldata_field(
+        &self,
+        calldata: &NestedCalldata,
+    ) -> Result<FieldMetadata, QueryBuilderError> {
+        let argument_index = self.argument_index(calldata.argument())?;
+        let field = self.argument_field(argument_index)?;
+        match (&field.sol_type, calldata.element()) {
+            (DynSolType::Bytes, None) => Ok(field),
+            (DynSolType::Array(element_type), Some(element))
+                if **element_type == DynSolType::Bytes =>
+            {
+                match field.children.get(element) {
+                    Some(child) => Ok(child.clone()),
+                    None => Err(QueryBuilderError::MissingDataInCalldataOffsets),
+                }
             }
+            _ => Err(QueryBuilderError::ArgumentIsNotNestedCalldata(
+                calldata.argument().to_string(),
+            )),
+        }
+    }
+
+    fn inner_calldata(
+        &self,
+        field: FieldMetadata,
+    ) -> Result<(Bytes, FieldMetadata), QueryBuilderError> {
+        let inner_calldata = Bytes::from(field.value.clone().unwrap_or_default());
+        let inner_data_field = FieldMetadata {
+            offset: self.data_field.offset + self.selector_size + field.offset,
+            size: Some(inner_calldata.len()),
+            ..field
+        };
+        Ok((inner_calldata, inner_data_field))
+    }
+
+    /// Value of an `address` argument, e.g. the target of an inner call.
+    pub(crate) fn address_argument(&self, name: &str) -> Result<Address, QueryBuilderError> {
+        let field = self.argument_field(self.argument_index(name)?)?;
+        match (&field.sol_type, &field.value) {
+            (DynSolType::Address, Some(word)) => Ok(Address::from_slice(&word[12..])),
+            _ => Err(QueryBuilderError::ArgumentIsNotAnAddress(name.to_string())),
+        }
+    }
+
+    fn argument_index(&self, name: &str) -> Result<usize, QueryBuilderError> {
+        let argument_index = self
+            .matched_function
+            .inputs
+            .iter()
+            .position(|argument| argument.name().eq(name));
+
+        match argument_index {
+            Some(argument_index) => Ok(argument_index),
+            None => Err(QueryBuilderError::CannotFindArgumentInFunction(
+                self.matched_function.clone(),
+                name.to_string(),
+            )),
         }
+    }
 
+    pub(crate) fn argument_field(
+        &self,
+        argument_index: usize,
+    ) -> Result<FieldMetadata, QueryBuilderError> {
         let data_size = match self.data_field.size {
             Some(s) => s,
             None => return Err(QueryBuilderError::DataFieldMissingSize),
         };
 
-        let matched_argument_index = match found_argument_index {
-            Some(ma) => ma,
-            None => {
-                return Err(QueryBuilderError::CannotFindArgumentInFunction(
-                    self.matched_function.clone(),
-                    name,
-                ))
-            }
-        };
-
         // we have the types :)
         let mut calldata_sol_types = Vec::new();
         for input in self.matched_function.inputs.clone() {
@@ -86,8 +263,11 @@ impl QueryBuilderForFunction {
         }
 
         // now we need to decode the contract call, but only from the slice of FUNCTION_SIGNITURE_SIZE...onwards..
-        let data = self.tx.inner.input().as_ref();
-        let sliced_data = &data[FUNCTION_SIGNATURE_SIZE..data_size];
+        let data = self.calldata.as_ref();
+        let sliced_data = match data.get(self.selector_size..data_size) {
+            Some(sliced_data) => sliced_data,
+            None => return Err(QueryBuilderError::DataFieldNotLongEnoughForSignatureExtraction),
+        };
 
         // compute the offsets :)
         let data_computed_offsets = match compute_abi_offsets(calldata_sol_types, sliced_data) {
@@ -95,18 +275,31 @@ impl QueryBuilderForFunction {
             Err(_) => return Err(QueryBuilderError::FailedToComputeOffsetsForCalldata),
         };
 
-        match data_computed_offsets.get(matched_argument_index) {
-            Some(field) => match field.size {
-                Some(size) => {
-                    self.selected_offsets.push((
-                        self.data_field.offset + FUNCTION_SIGNATURE_SIZE + field.offset,
-                        size,
-                    ));
-                    Ok(self)
-                }
-                None => Err(QueryBuilderError::TryingToGetSizeOfDynamicType),
-            },
+        match data_computed_offsets.into_iter().nth(argument_index) {
+            Some(field) => Ok(field),
             None => Err(QueryBuilderError::MissingDataInCalldataOffsets),
         }
     }
 }
+
+pub(crate) fn calldata_selector_of(calldata: &[u8]) -> Result<[u8; 4], QueryBuilderError> {
+    match calldata.first_chunk::<FUNCTION_SIGNATURE_SIZE>() {
+        Some(selector) => Ok(*selector),
+        None => Err(QueryBuilderError::DataFieldNotLongEnoughForSignatureExtraction),
+    }
+}
+
+/// Offsets computed against the calldata of another function would be meaningless.
+pub(crate) fn check_function_matches_calldata(
+    function: &Function,
+    calldata: &[u8],
+) -> Result<(), QueryBuilderError> {
+    let calldata_selector = calldata_selector_of(calldata)?;
+    if function.selector().0 != calldata_selector {
+        return Err(QueryBuilderError::FunctionDoesNotMatchCalldata {
+            function: function.full_signature(),
+            calldata_selector: format!("0x{}", hex::encode(calldata_selector)),
+        });
+    }
+    Ok(())
+}
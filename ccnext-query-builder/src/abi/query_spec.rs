@@ -0,0 +1,309 @@
+use alloy::{dyn_abi::DecodedEvent, rpc::types::Log};
+use alloy_json_abi::Event;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    log_filter::{argument_equals, ArgumentMatchError},
+    models::{QueryBuilderError, QueryableFields},
+    query_builder::QueryBuilder,
+    query_builder_for_event::QueryBuilderForEvent,
+    query_builder_for_function::QueryBuilderForFunction,
+};
+
+/// Declarative description of a proving query. It can be written in JSON or TOML and
+/// applied to any transaction through [`QueryBuilder::apply_spec`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct QuerySpec {
+    /// Static transaction/receipt fields, selected in order.
+    #[serde(default)]
+    pub fields: Vec<QueryableFields>,
+    /// Calldata segments of the called function.
+    #[serde(default)]
+    pub function: Option<FunctionSpec>,
+    /// Events to select segments from, applied in order.
+    #[serde(default)]
+    pub events: Vec<EventSpec>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FunctionSpec {
+    /// Function name, `0x` prefixed 4 byte selector, signature (`burn(uint256)`) or
+    /// human-readable fragment.
+    pub name_or_signature: String,
+    /// Select the 4 byte function selector.
+    #[serde(default)]
+    pub signature: bool,
+    /// Names of the arguments to select.
+    #[serde(default)]
+    pub arguments: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EventSpec {
+    /// Event name, `0x` prefixed 32 byte topic, signature
+    /// (`Transfer(address,address,uint256)`) or human-readable fragment.
+    pub name_or_signature: String,
+    /// Only keep logs whose decoded arguments equal these values.
+    #[serde(default)]
+    pub filters: Vec<ArgumentFilter>,
+    /// Only keep the log at this index of the receipt.
+    #[serde(default)]
+    pub log_index: Option<usize>,
+    /// What to do when more than one log matches.
+    #[serde(default)]
+    pub occurrence: EventOccurrence,
+    /// Select the address of the emitting contract.
+    #[serde(default)]
+    pub address: bool,
+    /// Select the event signature (topic 0).
+    #[serde(default)]
+    pub signature: bool,
+    /// Names of the arguments to select.
+    #[serde(default)]
+    pub arguments: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventOccurrence {
+    /// Exactly one log must match.
+    #[default]
+    Single,
+    /// Take the first matching log.
+    First,
+    /// Select from every matching log.
+    All,
+}
+
+/// Matches a decoded event argument against a value written the same way as in
+/// `cast`, e.g. `"0x1f1e...9aa3"`, `"1000000000000000000"` or `"true"`.
+///
+/// Indexed `string`, `bytes`, arrays and structs only exist as a hash in the log, so the
+/// value is hashed before comparing.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ArgumentFilter {
+    pub argument: String,
+    pub equals: String,
+}
+
+#[derive(Debug, Clone)]
+pub enum QuerySpecError {
+    FailedToParse(String),
+    EmptySelection {
+        path: String,
+    },
+    UnknownEventArgument {
+        path: String,
+        event: String,
+        argument: String,
+    },
+    InvalidFilterValue {
+        path: String,
+        argument: String,
+        value: String,
+    },
+    NoMatchingEvent {
+        path: String,
+    },
+    AmbiguousEventMatch {
+        path: String,
+        log_indexes: Vec<usize>,
+    },
+    StepFailed {
+        path: String,
+        error: Box<QueryBuilderError>,
+    },
+}
+
+impl QuerySpec {
+    pub fn from_json_str(spec: &str) -> Result<Self, QuerySpecError> {
+        serde_json::from_str(spec).map_err(|e| QuerySpecError::FailedToParse(e.to_string()))
+    }
+
+    #[cfg(feature = "toml")]
+    pub fn from_toml_str(spec: &str) -> Result<Self, QuerySpecError> {
+        toml::from_str(spec).map_err(|e| QuerySpecError::FailedToParse(e.to_string()))
+    }
+}
+
+impl FunctionSpec {
+    fn configure(&self, builder: &mut QueryBuilderForFunction) -> Result<(), QueryBuilderError> {
+        if self.signature {
+            builder.add_signature()?;
+        }
+        for argument in &self.arguments {
+            builder.add_argument(argument.clone())?;
+        }
+        Ok(())
+    }
+}
+
+impl EventSpec {
+    fn configure(&self, builder: &mut QueryBuilderForEvent) -> Result<(), QueryBuilderError> {
+        if self.address {
+            builder.add_address()?;
+        }
+        if self.signature {
+            builder.add_signature()?;
+        }
+        for argument in &self.arguments {
+            builder.add_argument(argument)?;
+        }
+        Ok(())
+    }
+
+    fn matches(
+        &self,
+        path: &str,
+        decoded_event: &DecodedEvent,
+        log_index: usize,
+        event: &Event,
+    ) -> Result<bool, QuerySpecError> {
+        if self.log_index.is_some_and(|expected| expected != log_index) {
+            return Ok(false);
+        }
+
+        for (filter_index, filter) in self.filters.iter().enumerate() {
+            let filter_path = format!("{path}.filters[{filter_index}]");
+            if !filter.matches(&filter_path, decoded_event, event)? {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+impl ArgumentFilter {
+    fn matches(
+        &self,
+        path: &str,
+        decoded_event: &DecodedEvent,
+        event: &Event,
+    ) -> Result<bool, QuerySpecError> {
+        argument_equals(event, decoded_event, &self.argument, &self.equals).map_err(|error| {
+            match error {
+                ArgumentMatchError::UnknownArgument => QuerySpecError::UnknownEventArgument {
+                    path: path.to_string(),
+                    event: event.name.clone(),
+                    argument: self.argument.clone(),
+                },
+                ArgumentMatchError::InvalidValue => QuerySpecError::InvalidFilterValue {
+                    path: path.to_string(),
+                    argument: self.argument.clone(),
+                    value: self.equals.clone(),
+                },
+            }
+        })
+    }
+}
+
+fn step_failed(path: String) -> impl FnOnce(QueryBuilderError) -> QueryBuilderError {
+    move |error| {
+        QueryBuilderError::InvalidQuerySpec(QuerySpecError::StepFailed {
+            path,
+            error: Box::new(error),
+        })
+    }
+}
+
+impl QueryBuilder {
+    /// Applies a [`QuerySpec`] to the transaction this builder was created from.
+    ///
+    /// Errors are wrapped in [`QueryBuilderError::InvalidQuerySpec`], carrying the path of
+    /// the offending spec entry (e.g. `events[1].filters[0]`). A spec that fails leaves the
+    /// selection as it was before applying it.
+    pub async fn apply_spec(&mut self, spec: &QuerySpec) -> Result<&mut Self, QueryBuilderError> {
+        let selected_offsets_count = self.selected_offsets_count();
+        if let Err(error) = self.apply_spec_steps(spec).await {
+            self.truncate_selected_offsets(selected_offsets_count);
+            return Err(error);
+        }
+        Ok(self)
+    }
+
+    async fn apply_spec_steps(&mut self, spec: &QuerySpec) -> Result<(), QueryBuilderError> {
+        for (field_index, field) in spec.fields.iter().enumerate() {
+            self.add_static_field(field.clone())
+                .map_err(step_failed(format!("fields[{field_index}]")))?;
+        }
+
+        if let Some(function) = &spec.function {
+            if !function.signature && function.arguments.is_empty() {
+                return Err(QueryBuilderError::InvalidQuerySpec(
+                    QuerySpecError::EmptySelection {
+                        path: "function".into(),
+                    },
+                ));
+            }
+
+            self.function_builder(function.name_or_signature.clone(), |builder| {
+                function.configure(builder)
+            })
+            .await
+            .map_err(step_failed("function".into()))?;
+        }
+
+        for (event_index, event_spec) in spec.events.iter().enumerate() {
+            let path = format!("events[{event_index}]");
+            if !event_spec.address && !event_spec.signature && event_spec.arguments.is_empty() {
+                return Err(QueryBuilderError::InvalidQuerySpec(
+                    QuerySpecError::EmptySelection { path },
+                ));
+            }
+
+            let candidates = self
+                .find_all_events(
+                    event_spec.name_or_signature.clone(),
+                    |_log: Log, _decoded_event, _log_index| true,
+                )
+                .await
+                .map_err(step_failed(path.clone()))?;
+
+            let mut matched_events = Vec::new();
+            for candidate in candidates {
+                let (_, decoded_event, log_index, event) = &candidate;
+                if event_spec
+                    .matches(&path, decoded_event, *log_index, event)
+                    .map_err(QueryBuilderError::InvalidQuerySpec)?
+                {
+                    matched_events.push(candidate);
+                }
+            }
+
+            if matched_events.is_empty() {
+                return Err(QueryBuilderError::InvalidQuerySpec(
+                    QuerySpecError::NoMatchingEvent { path },
+                ));
+            }
+
+            match event_spec.occurrence {
+                EventOccurrence::Single if matched_events.len() > 1 => {
+                    return Err(QueryBuilderError::InvalidQuerySpec(
+                        QuerySpecError::AmbiguousEventMatch {
+                            path,
+                            log_indexes: matched_events.iter().map(|m| m.2).collect(),
+                        },
+                    ));
+                }
+                EventOccurrence::Single | EventOccurrence::First => {
+                    matched_events.truncate(1);
+                }
+                EventOccurrence::All => {}
+            }
+
+            for matched_event in matched_events {
+                self.select_from_matched_event(matched_event, |builder| {
+                    event_spec.configure(builder)
+                })
+                .map_err(step_failed(path.clone()))?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,472 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, RwLock,
+    },
+};
+
+use alloy::{json_abi::JsonAbi, primitives::Address};
+use futures::{stream, StreamExt};
+use tokio::sync::OnceCell;
+
+use super::{
+    models::{AbiRequestContext, QueryBuilderError},
+    query_builder::AbiProvider,
+};
+
+/// How many ABIs [`AbiRegistry::get_abis`] fetches at the same time by default.
+pub const DEFAULT_MAX_CONCURRENT_REQUESTS: usize = 8;
+/// How many ABIs an [`AbiRegistry`] keeps by default before evicting the least recently
+/// used one.
+pub const DEFAULT_MAX_ENTRIES: usize = 10_000;
+/// Past the cap, an eighth of the entries is evicted at once.
+const EVICTION_BATCH_DIVISOR: usize = 8;
+
+/// Parsed ABIs shared between any number of [`super::query_builder::QueryBuilder`]s.
+///
+/// Cloning is cheap and every clone sees the same cache, so one registry can be handed
+/// to builders running on different tasks or threads. Lookups only take a read lock; the
+/// provider is called without holding any lock.
+///
+/// ABIs are cached per chain and address, and per block as well when
+/// [`Self::with_per_block_cache`] is enabled, up to [`Self::with_max_entries`] of them.
+#[derive(Clone)]
+pub struct AbiRegistry {
+    provider: Arc<dyn AbiProvider>,
+    abis: Arc<RwLock<HashMap<AbiRequestContext, CacheEntry>>>,
+    // ticks on every lookup, the entry with the oldest tick is evicted first.
+    clock: Arc<AtomicU64>,
+    max_concurrent_requests: usize,
+    max_entries: usize,
+    per_block_cache: bool,
+}
+
+struct CacheEntry {
+    // one cell per key, so concurrent lookups of the same contract share one request.
+    cell: Arc<OnceCell<JsonAbi>>,
+    last_used: AtomicU64,
+}
+
+/// Outcome of [`AbiRegistry::get_abis`], one entry per requested address.
+#[derive(Debug, Default)]
+pub struct AbiBatch {
+    pub abis: HashMap<Address, JsonAbi>,
+    /// Sorted by address.
+    pub failures: Vec<(Address, QueryBuilderError)>,
+}
+
+impl AbiRegistry {
+    pub fn new(provider: impl AbiProvider + 'static) -> Self {
+        Self::from_provider(Arc::new(provider))
+    }
+
+    pub fn from_provider(provider: Arc<dyn AbiProvider>) -> Self {
+        Self {
+            provider,
+            abis: Arc::new(RwLock::new(HashMap::new())),
+            clock: Arc::new(AtomicU64::new(0)),
+            max_concurrent_requests: DEFAULT_MAX_CONCURRENT_REQUESTS,
+            max_entries: DEFAULT_MAX_ENTRIES,
+            per_block_cache: false,
+        }
+    }
+
+    /// Caps how many provider requests a single [`Self::get_abis`] call keeps in flight.
+    pub fn with_max_concurrent_requests(mut self, max_concurrent_requests: usize) -> Self {
+        self.max_concurrent_requests = max_concurrent_requests.max(1);
+        self
+    }
+
+    /// Caps how many ABIs are kept. Past it the least recently used ones are evicted, an
+    /// eighth of `max_entries` at a time.
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries.max(1);
+        self
+    }
+
+    /// Caches ABIs per block too, for providers that answer with the ABI a contract had
+    /// at that height (e.g. behind an upgradeable proxy). Off by default, since every new
+    /// block is then a cache miss; without it a registry over a
+    /// [`super::providers::proxy::ProxyResolvingAbiProvider`] keeps serving the
+    /// implementation ABI it first saw after the proxy is upgraded.
+    pub fn with_per_block_cache(mut self, per_block_cache: bool) -> Self {
+        self.per_block_cache = per_block_cache;
+        self
+    }
+
+    pub fn provider(&self) -> &Arc<dyn AbiProvider> {
+        &self.provider
+    }
+
+    /// Returns the cached ABI, fetching and parsing it through the provider on a miss.
+    /// Callers asking for a contract that is already being fetched wait for that request
+    /// instead of sending their own. Failed lookups are not cached.
+    pub async fn get_abi(&self, context: &AbiRequestContext) -> Result<JsonAbi, QueryBuilderError> {
+        let cell = self.cell(context);
+        let result = cell
+            .get_or_try_init(|| self.fetch_abi(context))
+            .await
+            .cloned();
+        if result.is_err() {
+            self.remove_failed(context, &cell);
+        }
+        result
+    }
+
+    /// Resolves every context, at most `max_concurrent_requests` at a time. Unlike
+    /// [`Self::get_abi`] one failing address doesn't stop the others.
+    pub async fn get_abis<I>(&self, contexts: I) -> AbiBatch
+    where
+        I: IntoIterator<Item = AbiRequestContext>,
+    {
+        let mut unique_contexts: Vec<AbiRequestContext> = contexts.into_iter().collect();
+        unique_contexts.sort_by_key(|c| (c.address, c.chain_id, c.block_number));
+        unique_contexts.dedup();
+
+        let results: Vec<_> = stream::iter(unique_contexts)
+            .map(|context| async move { (context.address, self.get_abi(&context).await) })
+            .buffer_unordered(self.max_concurrent_requests)
+            .collect()
+            .await;
+
+        let mut batch = AbiBatch::default();
+        for (address, result) in results {
+            match result {
+                Ok(abi) => {
+                    batch.abis.insert(address, abi);
+                }
+                Err(e) => batch.failures.push((address, e)),
+            }
+        }
+        batch.failures.sort_by_key(|(address, _)| *address);
+        batch
+    }
+
+    /// Always asks the provider, bypassing and not updating the cache.
+    pub async fn fetch_abi(
+        &self,
+        context: &AbiRequestContext,
+    ) -> Result<JsonAbi, QueryBuilderError> {
+        let abi_raw = self.provider.get_abi(context).await?;
+
+        match JsonAbi::from_json_str(&abi_raw) {
+            Ok(json_abi) => Ok(json_abi),
+            Err(_) => Err(QueryBuilderError::FailedToParseAbi(
+                context.address.to_string(),
+                abi_raw,
+            )),
+        }
+    }
+
+    pub fn get_cached(&self, context: &AbiRequestContext) -> Option<JsonAbi> {
+        self.read()
+            .get(&self.cache_key(context))
+            .and_then(|entry| self.touch(entry).get().cloned())
+    }
+
+    /// Seeds the cache, e.g. with ABIs known ahead of time.
+    pub fn insert(&self, context: &AbiRequestContext, abi: JsonAbi) {
+        let key = self.cache_key(context);
+        let entry = CacheEntry {
+            cell: Arc::new(OnceCell::new_with(Some(abi))),
+            last_used: AtomicU64::new(self.tick()),
+        };
+        let mut abis = self.write();
+        abis.insert(key, entry);
+        self.evict_least_recently_used(&mut abis, &key);
+    }
+
+    pub fn len(&self) -> usize {
+        self.read()
+            .values()
+            .filter(|entry| entry.cell.initialized())
+            .count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn cache_key(&self, context: &AbiRequestContext) -> AbiRequestContext {
+        AbiRequestContext {
+            block_number: context.block_number.filter(|_| self.per_block_cache),
+            ..*context
+        }
+    }
+
+    fn cell(&self, context: &AbiRequestContext) -> Arc<OnceCell<JsonAbi>> {
+        let key = self.cache_key(context);
+        if let Some(entry) = self.read().get(&key) {
+            return self.touch(entry).clone();
+        }
+
+        let mut abis = self.write();
+        let cell = match abis.get(&key) {
+            Some(entry) => return self.touch(entry).clone(),
+            None => Arc::new(OnceCell::new()),
+        };
+        abis.insert(
+            key,
+            CacheEntry {
+                cell: cell.clone(),
+                last_used: AtomicU64::new(self.tick()),
+            },
+        );
+        self.evict_least_recently_used(&mut abis, &key);
+        cell
+    }
+
+    /// Drops the cell of a failed lookup, unless another caller has filled or replaced it.
+    fn remove_failed(&self, context: &AbiRequestContext, cell: &Arc<OnceCell<JsonAbi>>) {
+        let key = self.cache_key(context);
+        let mut abis = self.write();
+        if abis
+            .get(&key)
+            .is_some_and(|entry| Arc::ptr_eq(&entry.cell, cell) && !entry.cell.initialized())
+        {
+            abis.remove(&key);
+        }
+    }
+
+    fn tick(&self) -> u64 {
+        self.clock.fetch_add(1, Ordering::Relaxed)
+    }
+
+    fn touch<'a>(&self, entry: &'a CacheEntry) -> &'a Arc<OnceCell<JsonAbi>> {
+        entry.last_used.store(self.tick(), Ordering::Relaxed);
+        &entry.cell
+    }
+
+    /// Evicts down to `max_entries - max_entries / EVICTION_BATCH_DIVISOR` in one pass once
+    /// the cap is passed, so the write lock is only held for a scan every so many inserts.
+    /// Lookups still in flight are kept, so the map may briefly hold more entries.
+    fn evict_least_recently_used(
+        &self,
+        abis: &mut HashMap<AbiRequestContext, CacheEntry>,
+        keep: &AbiRequestContext,
+    ) {
+        if abis.len() <= self.max_entries {
+            return;
+        }
+
+        let target_len = self.max_entries - self.max_entries / EVICTION_BATCH_DIVISOR;
+        let mut candidates: Vec<(u64, AbiRequestContext)> = abis
+            .iter()
+            .filter(|(key, entry)| *key != keep && entry.cell.initialized())
+            .map(|(key, entry)| (entry.last_used.load(Ordering::Relaxed), *key))
+            .collect();
+        let evict_count = (abis.len() - target_len).min(candidates.len());
+        if evict_count == 0 {
+            return;
+        }
+        if evict_count < candidates.len() {
+            candidates.select_nth_unstable_by_key(evict_count - 1, |(last_used, _)| *last_used);
+        }
+        for (_, key) in &candidates[..evict_count] {
+            abis.remove(key);
+        }
+    }
+
+    // a panic while holding the lock can't leave the map half updated, so poisoning is ignored.
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, HashMap<AbiRequestContext, CacheEntry>> {
+        self.abis
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn write(&self) -> std::sync::RwLockWriteGuard<'_, HashMap<AbiRequestContext, CacheEntry>> {
+        self.abis
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        time::Duration,
+    };
+
+    use async_trait::async_trait;
+
+    const UNVERIFIED: Address = Address::with_last_byte(0xee);
+    const BROKEN: Address = Address::with_last_byte(0xbb);
+
+    /// Answers after a short delay and keeps track of how many requests overlap.
+    #[derive(Default)]
+    struct SlowProvider {
+        calls: AtomicUsize,
+        in_flight: AtomicUsize,
+        max_in_flight: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl AbiProvider for SlowProvider {
+        async fn get_abi(&self, context: &AbiRequestContext) -> Result<String, QueryBuilderError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            let in_flight = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_in_flight.fetch_max(in_flight, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+
+            match context.address {
+                UNVERIFIED => Err(QueryBuilderError::NoAbiFoundForContract(
+                    context.address.to_string(),
+                )),
+                BROKEN => Ok("not json".into()),
+                _ => Ok("[]".into()),
+            }
+        }
+    }
+
+    fn contexts(addresses: &[Address]) -> Vec<AbiRequestContext> {
+        addresses
+            .iter()
+            .map(|address| AbiRequestContext::new(*address).with_chain_id(11155111))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn batches_are_fetched_concurrently_up_to_the_limit() {
+        let provider = Arc::new(SlowProvider::default());
+        let registry = AbiRegistry::from_provider(provider.clone()).with_max_concurrent_requests(3);
+
+        let addresses: Vec<Address> = (0..10).map(Address::with_last_byte).collect();
+        let batch = registry.get_abis(contexts(&addresses)).await;
+
+        assert_eq!(batch.abis.len(), 10);
+        assert!(batch.failures.is_empty());
+        assert_eq!(provider.max_in_flight.load(Ordering::SeqCst), 3);
+
+        // everything is cached now.
+        registry.get_abis(contexts(&addresses)).await;
+        assert_eq!(provider.calls.load(Ordering::SeqCst), 10);
+    }
+
+    #[tokio::test]
+    async fn in_flight_requests_are_shared() {
+        let provider = Arc::new(SlowProvider::default());
+        let registry = AbiRegistry::from_provider(provider.clone());
+        let other_handle = registry.clone();
+        let context = AbiRequestContext::new(Address::with_last_byte(1));
+
+        let (first, second, batch) = tokio::join!(
+            registry.get_abi(&context),
+            other_handle.get_abi(&context),
+            registry.get_abis(vec![context, context]),
+        );
+        assert!(first.is_ok() && second.is_ok());
+        assert_eq!(batch.abis.len(), 1);
+        assert_eq!(provider.calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn failures_are_reported_per_address_and_not_cached() {
+        let provider = Arc::new(SlowProvider::default());
+        let registry = AbiRegistry::from_provider(provider.clone());
+
+        let verified = Address::with_last_byte(1);
+        let addresses = [UNVERIFIED, verified, BROKEN];
+        let batch = registry.get_abis(contexts(&addresses)).await;
+
+        assert_eq!(batch.abis.keys().collect::<Vec<_>>(), vec![&verified]);
+        assert!(matches!(
+            batch.failures.as_slice(),
+            [
+                (BROKEN, QueryBuilderError::FailedToParseAbi(..)),
+                (UNVERIFIED, QueryBuilderError::NoAbiFoundForContract(_)),
+            ]
+        ));
+        assert_eq!(registry.len(), 1);
+        // failed lookups leave nothing behind.
+        assert_eq!(registry.read().len(), 1);
+
+        registry.get_abis(contexts(&addresses)).await;
+        assert_eq!(provider.calls.load(Ordering::SeqCst), 5);
+    }
+
+    #[tokio::test]
+    async fn cache_is_keyed_by_chain_and_optionally_by_block() {
+        let provider = Arc::new(SlowProvider::default());
+        let registry = AbiRegistry::from_provider(provider.clone());
+
+        let sepolia = AbiRequestContext::new(Address::with_last_byte(1))
+            .with_chain_id(11155111)
+            .with_block_number(100);
+        let mainnet = AbiRequestContext {
+            chain_id: Some(1),
+            ..sepolia
+        };
+        let later_block = sepolia.with_block_number(200);
+
+        for context in [sepolia, mainnet, later_block] {
+            registry.get_abi(&context).await.unwrap();
+        }
+        assert_eq!(provider.calls.load(Ordering::SeqCst), 2);
+        assert_eq!(registry.len(), 2);
+
+        let per_block = AbiRegistry::from_provider(provider.clone()).with_per_block_cache(true);
+        for context in [sepolia, later_block, sepolia] {
+            per_block.get_abi(&context).await.unwrap();
+        }
+        assert_eq!(provider.calls.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    async fn least_recently_used_abis_are_evicted_past_the_cap() {
+        let provider = Arc::new(SlowProvider::default());
+        let registry = AbiRegistry::from_provider(provider.clone()).with_max_entries(2);
+        let [first, second, third] = [1, 2, 3].map(|byte| {
+            AbiRequestContext::new(Address::with_last_byte(byte)).with_chain_id(11155111)
+        });
+
+        registry.get_abi(&first).await.unwrap();
+        registry.get_abi(&second).await.unwrap();
+        // `first` is now more recent than `second`.
+        registry.get_abi(&first).await.unwrap();
+        registry.get_abi(&third).await.unwrap();
+        assert_eq!(registry.len(), 2);
+        assert!(registry.get_cached(&first).is_some());
+        assert!(registry.get_cached(&second).is_none());
+        assert_eq!(provider.calls.load(Ordering::SeqCst), 3);
+
+        registry.insert(&second, JsonAbi::new());
+        assert_eq!(registry.len(), 2);
+        assert!(registry.get_cached(&third).is_none());
+    }
+
+    #[test]
+    fn a_batch_of_least_recently_used_abis_is_evicted_at_once() {
+        let registry = AbiRegistry::new(SlowProvider::default()).with_max_entries(16);
+        let contexts: Vec<AbiRequestContext> = (0..20)
+            .map(|byte| AbiRequestContext::new(Address::with_last_byte(byte)).with_chain_id(1))
+            .collect();
+
+        for context in &contexts[..16] {
+            registry.insert(context, JsonAbi::new());
+        }
+        // the first two are now the most recently used.
+        assert!(registry.get_cached(&contexts[0]).is_some());
+        assert!(registry.get_cached(&contexts[1]).is_some());
+
+        // one past the cap evicts down to 14, the three least recently used.
+        registry.insert(&contexts[16], JsonAbi::new());
+        assert_eq!(registry.len(), 14);
+        for (index, context) in contexts[..17].iter().enumerate() {
+            assert_eq!(
+                registry.get_cached(context).is_none(),
+                (2..5).contains(&index),
+                "{index}"
+            );
+        }
+
+        // the next inserts fit without evicting.
+        registry.insert(&contexts[17], JsonAbi::new());
+        registry.insert(&contexts[18], JsonAbi::new());
+        assert_eq!(registry.len(), 16);
+    }
+}
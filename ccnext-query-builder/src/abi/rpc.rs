@@ -0,0 +1,56 @@
+use alloy::{
+    primitives::B256,
+    providers::Provider,
+    rpc::types::{Transaction, TransactionReceipt},
+};
+
+use super::models::QueryBuilderError;
+
+/// Fetches a mined transaction and its receipt, making sure both describe the same
+/// transaction in the same block.
+pub async fn fetch_transaction_and_receipt<P: Provider>(
+    provider: &P,
+    tx_hash: B256,
+) -> Result<(Transaction, TransactionReceipt), QueryBuilderError> {
+    let tx = match provider
+        .get_transaction_by_hash(tx_hash)
+        .await
+        .map_err(|e| QueryBuilderError::ProviderRequestFailed(e.to_string()))?
+    {
+        Some(tx) => tx,
+        None => return Err(QueryBuilderError::TransactionNotFound(tx_hash)),
+    };
+
+    // a transaction without a block is still in the mempool, so it has no receipt yet.
+    let tx_block_hash = match tx.block_hash {
+        Some(block_hash) => block_hash,
+        None => return Err(QueryBuilderError::TransactionPending(tx_hash)),
+    };
+
+    let rx = match provider
+        .get_transaction_receipt(tx_hash)
+        .await
+        .map_err(|e| QueryBuilderError::ProviderRequestFailed(e.to_string()))?
+    {
+        Some(rx) => rx,
+        None => return Err(QueryBuilderError::ReceiptNotFound(tx_hash)),
+    };
+
+    if rx.transaction_hash != tx_hash {
+        return Err(QueryBuilderError::ReceiptTransactionHashMismatch {
+            expected: tx_hash,
+            actual: rx.transaction_hash,
+        });
+    }
+
+    // both requests are separate, a reorg in between could leave us with data from two blocks.
+    if rx.block_hash != Some(tx_block_hash) {
+        return Err(QueryBuilderError::ReceiptBlockHashMismatch {
+            tx_hash,
+            tx_block_hash,
+            receipt_block_hash: rx.block_hash,
+        });
+    }
+
+    Ok((tx, rx))
+}
@@ -0,0 +1,218 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use alloy::{
+    dyn_abi::JsonAbiExt,
+    json_abi::{AbiItem, Event, Function},
+    primitives::{LogData, B256},
+};
+
+use super::{models::QueryBuilderError, utils::decode_log_exact};
+
+const BUNDLED_SIGNATURES: &str = include_str!("../../data/signatures.txt");
+
+/// Function and event fragments indexed by selector and topic0, used to decode calldata
+/// and logs of contracts without a known ABI.
+///
+/// Databases are plain text, one human-readable fragment per line (`function ...` or
+/// `event ...`), with `#` starting a comment. Events must keep their `indexed` markers,
+/// the topic count is what tells apart events that share a topic0.
+#[derive(Debug, Clone, Default)]
+pub struct SignatureDatabase {
+    functions: HashMap<[u8; 4], Vec<Function>>,
+    events: HashMap<B256, Vec<Event>>,
+}
+
+impl SignatureDatabase {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Common token, WETH, Uniswap V2, ownership and proxy signatures.
+    pub fn bundled() -> Self {
+        Self::parse(BUNDLED_SIGNATURES).expect("bundled signatures should parse")
+    }
+
+    pub fn parse(content: &str) -> Result<Self, QueryBuilderError> {
+        let mut database = Self::new();
+        database.extend_from_str(content)?;
+        Ok(database)
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, QueryBuilderError> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path).map_err(|e| {
+            QueryBuilderError::FailedToReadSignatureDatabase {
+                path: path.display().to_string(),
+                error_message: e.to_string(),
+            }
+        })?;
+        Self::parse(&content)
+    }
+
+    /// Adds every fragment of `content`, e.g. to layer project signatures over the bundled ones.
+    pub fn extend_from_str(&mut self, content: &str) -> Result<(), QueryBuilderError> {
+        for (line_index, line) in content.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let failed =
+                |error_message: String| QueryBuilderError::FailedToParseSignatureDatabase {
+                    line: line_index + 1,
+                    error_message,
+                };
+            match AbiItem::parse(line).map_err(|e| failed(e.to_string()))? {
+                AbiItem::Function(function) => self.add_function(function.into_owned()),
+                AbiItem::Event(event) => self.add_event(event.into_owned()),
+                _ => return Err(failed("only functions and events are supported".into())),
+            }
+        }
+        Ok(())
+    }
+
+    /// Duplicates are ignored.
+    pub fn add_function(&mut self, function: Function) {
+        let candidates = self.functions.entry(function.selector().0).or_default();
+        if !candidates.contains(&function) {
+            candidates.push(function);
+        }
+    }
+
+    /// Duplicates are ignored.
+    pub fn add_event(&mut self, event: Event) {
+        let candidates = self.events.entry(event.selector()).or_default();
+        if !candidates.contains(&event) {
+            candidates.push(event);
+        }
+    }
+
+    pub fn functions(&self, selector: [u8; 4]) -> &[Function] {
+        self.functions
+            .get(&selector)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    pub fn events(&self, topic0: &B256) -> &[Event] {
+        self.events
+            .get(topic0)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Candidates for the selector of `calldata` whose arguments decode and re-encode to
+    /// exactly the same bytes, which rules out most colliding selectors.
+    pub fn functions_for_calldata(&self, calldata: &[u8]) -> Vec<&Function> {
+        let (selector, arguments) = match calldata.split_first_chunk::<4>() {
+            Some(split) => split,
+            None => return Vec::new(),
+        };
+
+        self.functions(*selector)
+            .iter()
+            .filter(|function| {
+                function
+                    .abi_decode_input(arguments, true)
+                    .and_then(|values| function.abi_encode_input(&values))
+                    .is_ok_and(|encoded| encoded[4..] == *arguments)
+            })
+            .collect()
+    }
+
+    /// Candidates for the topic0 of `log` with the same number of indexed arguments that
+    /// can actually decode it.
+    pub fn events_for_log(&self, log: &LogData) -> Vec<&Event> {
+        let topic0 = match log.topics().first() {
+            Some(topic0) => topic0,
+            None => return Vec::new(),
+        };
+
+        self.events(topic0)
+            .iter()
+            .filter(|event| !event.anonymous && decode_log_exact(event, log).is_some())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use alloy::primitives::{hex, Address, U256};
+
+    #[test]
+    fn bundled_database_parses() {
+        let database = SignatureDatabase::bundled();
+        assert_eq!(
+            database.functions(hex!("42966c68"))[0].signature(),
+            "burn(uint256)"
+        );
+        // ERC-20 and ERC-721 `Transfer` share a topic0.
+        assert_eq!(
+            database
+                .events(
+                    &Event::parse("Transfer(address indexed,address indexed,uint256)")
+                        .unwrap()
+                        .selector()
+                )
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn parse_errors_point_at_the_line() {
+        let error = SignatureDatabase::parse(
+            "# comment\nfunction ok(uint256)\n\nstruct Nope { uint256 a; }",
+        )
+        .unwrap_err();
+        assert!(matches!(
+            error,
+            QueryBuilderError::FailedToParseSignatureDatabase { line: 4, .. }
+        ));
+    }
+
+    #[test]
+    fn colliding_selectors_are_narrowed_down_by_decoding_calldata() {
+        let database = SignatureDatabase::parse(
+            "function transferFrom(address from, address to, uint256 value)\nfunction gasprice_bit_ether(int128)",
+        )
+        .unwrap();
+        assert_eq!(database.functions(hex!("23b872dd")).len(), 2);
+
+        let transfer_from = &database.functions(hex!("23b872dd"))[0];
+        let calldata = transfer_from
+            .abi_encode_input(&[
+                Address::repeat_byte(0x11).into(),
+                Address::repeat_byte(0x22).into(),
+                U256::from(1).into(),
+            ])
+            .unwrap();
+        let candidates = database.functions_for_calldata(&calldata);
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].name, "transferFrom");
+    }
+
+    #[test]
+    fn events_sharing_a_topic0_are_told_apart_by_topic_count() {
+        let database = SignatureDatabase::bundled();
+        let erc721 = Event::parse(
+            "Transfer(address indexed from, address indexed to, uint256 indexed tokenId)",
+        )
+        .unwrap();
+        let log = LogData::new_unchecked(
+            vec![
+                erc721.selector(),
+                Address::repeat_byte(0x11).into_word(),
+                Address::repeat_byte(0x22).into_word(),
+                B256::from(U256::from(7)),
+            ],
+            Default::default(),
+        );
+
+        let candidates = database.events_for_log(&log);
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].inputs[2].name, "tokenId");
+    }
+}
@@ -0,0 +1,136 @@
+use std::marker::PhantomData;
+
+use super::{
+    models::QueryBuilderError, query_builder_for_event::QueryBuilderForEvent,
+    query_builder_for_function::QueryBuilderForFunction,
+};
+
+/// Argument of the `sol!` call or event `T`. Built with [`crate::sol_field!`], which only
+/// compiles if `T` has a field of that name.
+pub struct SolField<T> {
+    name: &'static str,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Clone for SolField<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for SolField<T> {}
+
+impl<T> SolField<T> {
+    /// Prefer [`crate::sol_field!`], nothing checks `name` here.
+    #[doc(hidden)]
+    pub const fn new_unchecked(name: &'static str) -> Self {
+        Self {
+            name,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// `sol!` names unnamed parameters `_0`, `_1`, ... after their position.
+    fn index_in<'a>(&self, names: impl IntoIterator<Item = &'a str>) -> Option<usize> {
+        let unnamed_index = self
+            .name
+            .strip_prefix('_')
+            .and_then(|index| index.parse::<usize>().ok());
+
+        names.into_iter().enumerate().position(|(index, name)| {
+            name == self.name || (name.is_empty() && Some(index) == unnamed_index)
+        })
+    }
+}
+
+/// `sol_field!(IERC20::transferCall, amount)` selects the `amount` argument of
+/// `transfer`. A typo or a renamed parameter is a compile error.
+#[macro_export]
+macro_rules! sol_field {
+    ($sol_type:ty, $field:ident) => {{
+        let _ = |value: &$sol_type| {
+            let _ = &value.$field;
+        };
+        $crate::abi::typed::SolField::<$sol_type>::new_unchecked(stringify!($field))
+    }};
+}
+
+/// [`QueryBuilderForFunction`] restricted to the arguments of the `sol!` call `T`.
+pub struct TypedFunctionBuilder<'a, T> {
+    inner: &'a mut QueryBuilderForFunction,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<'a, T> TypedFunctionBuilder<'a, T> {
+    pub(crate) fn new(inner: &'a mut QueryBuilderForFunction) -> Self {
+        Self {
+            inner,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn add_signature(&mut self) -> Result<&mut Self, QueryBuilderError> {
+        self.inner.add_signature()?;
+        Ok(self)
+    }
+
+    pub fn add_argument(&mut self, field: SolField<T>) -> Result<&mut Self, QueryBuilderError> {
+        let inputs = &self.inner.function().inputs;
+        match field.index_in(inputs.iter().map(|input| input.name.as_str())) {
+            Some(index) => {
+                self.inner.add_argument_at(index)?;
+                Ok(self)
+            }
+            None => Err(QueryBuilderError::CannotFindArgumentInFunction(
+                self.inner.function().clone(),
+                field.name().to_string(),
+            )),
+        }
+    }
+}
+
+/// [`QueryBuilderForEvent`] restricted to the arguments of the `sol!` event `T`.
+pub struct TypedEventBuilder<'a, T> {
+    inner: &'a mut QueryBuilderForEvent,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<'a, T> TypedEventBuilder<'a, T> {
+    pub(crate) fn new(inner: &'a mut QueryBuilderForEvent) -> Self {
+        Self {
+            inner,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn add_signature(&mut self) -> Result<&mut Self, QueryBuilderError> {
+        self.inner.add_signature()?;
+        Ok(self)
+    }
+
+    pub fn add_address(&mut self) -> Result<&mut Self, QueryBuilderError> {
+        self.inner.add_address()?;
+        Ok(self)
+    }
+
+    pub fn add_argument(&mut self, field: SolField<T>) -> Result<&mut Self, QueryBuilderError> {
+        let event = match self.inner.event() {
+            Some(event) => event,
+            None => return Err(QueryBuilderError::RawLogHasNoEvent),
+        };
+        match field.index_in(event.inputs.iter().map(|input| input.name.as_str())) {
+            Some(index) => {
+                self.inner.add_argument_at(index)?;
+                Ok(self)
+            }
+            None => Err(QueryBuilderError::UnknownEventArgument {
+                event: event.name.clone(),
+                argument: field.name().to_string(),
+            }),
+        }
+    }
+}
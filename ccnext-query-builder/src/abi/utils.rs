@@ -1,6 +1,8 @@
-use crate::abi::models::FieldMetadata;
+use crate::abi::models::{FieldMetadata, NormalizedSegments, SegmentPosition};
 use alloy::{
-    dyn_abi::{Decoder, DynSolType},
+    dyn_abi::{DecodedEvent, Decoder, DynSolType, EventExt},
+    json_abi::Event,
+    primitives::LogData,
     sol_types::Error,
 };
 
@@ -297,3 +299,61 @@ pub(crate) fn make_offsets_absolute(field: &mut FieldMetadata, base_offset: usiz
         make_offsets_absolute(child, base_offset);
     }
 }
+
+/// Sorts the selected `(offset, size)` ranges and merges the ones that overlap or touch,
+/// keeping track of where every original selection landed in the merged output.
+pub fn normalize_segments(selected_offsets: &[(usize, usize)]) -> NormalizedSegments {
+    let mut order: Vec<usize> = (0..selected_offsets.len()).collect();
+    order.sort_by_key(|index| selected_offsets[*index]);
+
+    let mut segments: Vec<(usize, usize)> = Vec::new();
+    let mut selections = vec![
+        SegmentPosition {
+            segment_index: 0,
+            offset_in_segment: 0,
+        };
+        selected_offsets.len()
+    ];
+
+    for index in order {
+        let (offset, size) = selected_offsets[index];
+        let end = offset + size;
+
+        match segments.last_mut() {
+            // overlapping or adjacent to the previous segment, so we extend it.
+            Some((segment_offset, segment_size)) if offset <= *segment_offset + *segment_size => {
+                let segment_end = (*segment_offset + *segment_size).max(end);
+                *segment_size = segment_end - *segment_offset;
+            }
+            _ => segments.push((offset, size)),
+        }
+
+        // segments is never empty at this point.
+        let segment_index = segments.len() - 1;
+        selections[index] = SegmentPosition {
+            segment_index,
+            offset_in_segment: offset - segments[segment_index].0,
+        };
+    }
+
+    NormalizedSegments {
+        segments,
+        selections,
+    }
+}
+
+/// Decodes `log` with `event` only if it has exactly the topics `event` implies, since
+/// events differing only in which arguments are indexed (e.g. ERC-20 and ERC-721
+/// `Transfer`) share a topic0.
+pub(crate) fn decode_log_exact(event: &Event, log: &LogData) -> Option<DecodedEvent> {
+    let indexed = event.inputs.iter().filter(|input| input.indexed).count();
+    let expected_topics = if event.anonymous {
+        indexed
+    } else {
+        indexed + 1
+    };
+    if log.topics().len() != expected_topics {
+        return None;
+    }
+    event.decode_log(log, true).ok()
+}
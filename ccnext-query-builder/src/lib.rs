@@ -1,4 +1,14 @@
+// lets `#[derive(CcnextQuery)]` refer to `::ccnext_query_builder` inside this crate too.
+extern crate self as ccnext_query_builder;
+
 pub mod abi;
+#[cfg(test)]
+pub mod mock_http;
+#[cfg(test)]
+pub mod mock_transport;
 pub mod test_helpers;
 #[cfg(test)]
 pub mod tests;
+
+pub use abi::ccnext_query::CcnextQuery;
+pub use ccnext_query_builder_derive::CcnextQuery;
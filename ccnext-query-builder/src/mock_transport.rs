@@ -0,0 +1,95 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+
+use alloy::{
+    providers::{Provider, RootProvider},
+    rpc::{
+        client::RpcClient,
+        json_rpc::{RequestPacket, ResponsePacket, SerializedRequest},
+    },
+    transports::{TransportError, TransportErrorKind, TransportFut},
+};
+use serde_json::{json, Value};
+
+/// In-memory JSON-RPC transport. Results are queued per method and handed out in the
+/// order they were pushed, so tests can script exactly what a node would answer.
+#[derive(Clone, Default)]
+pub struct MockTransport {
+    responses: Arc<Mutex<HashMap<String, VecDeque<Value>>>>,
+    requests: Arc<Mutex<Vec<(String, Value)>>>,
+}
+
+impl MockTransport {
+    pub fn push_response(&self, method: &str, result: Value) -> &Self {
+        self.responses
+            .lock()
+            .unwrap()
+            .entry(method.to_string())
+            .or_default()
+            .push_back(result);
+        self
+    }
+
+    /// Every `(method, params)` pair received so far.
+    pub fn requests(&self) -> Vec<(String, Value)> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    pub fn provider(&self) -> impl Provider + Clone {
+        RootProvider::new(RpcClient::new(self.clone(), true))
+    }
+
+    fn respond(&self, request: &SerializedRequest) -> Result<Value, TransportError> {
+        let params = request
+            .params()
+            .map(|p| serde_json::from_str(p.get()).unwrap_or(Value::Null))
+            .unwrap_or(Value::Null);
+        self.requests
+            .lock()
+            .unwrap()
+            .push((request.method().to_string(), params));
+
+        let result = self
+            .responses
+            .lock()
+            .unwrap()
+            .get_mut(request.method())
+            .and_then(|queue| queue.pop_front())
+            .ok_or_else(|| {
+                TransportErrorKind::custom_str(&format!(
+                    "no mocked response for {}",
+                    request.method()
+                ))
+            })?;
+
+        Ok(json!({ "jsonrpc": "2.0", "id": request.id(), "result": result }))
+    }
+}
+
+impl tower::Service<RequestPacket> for MockTransport {
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: RequestPacket) -> Self::Future {
+        let response = match &request {
+            RequestPacket::Single(single) => self.respond(single),
+            RequestPacket::Batch(batch) => batch
+                .iter()
+                .map(|single| self.respond(single))
+                .collect::<Result<Vec<_>, _>>()
+                .map(Value::Array),
+        };
+
+        Box::pin(
+            async move { serde_json::from_value(response?).map_err(TransportErrorKind::custom) },
+        )
+    }
+}
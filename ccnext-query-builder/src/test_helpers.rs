@@ -3,15 +3,17 @@ use alloy::eips::eip2930::AccessList;
 use alloy::rpc::types::eth::transaction::Transaction;
 use alloy::{
     primitives::{Address, B256, U256},
-    providers::{Provider, ProviderBuilder},
+    providers::ProviderBuilder,
     rpc::types::TransactionReceipt,
 };
 use async_trait::async_trait;
 use ccnext_abi_encoding::common::{compute_v, compute_y_parity};
+use serde_json::{json, Value};
 use std::str::FromStr;
 
-use crate::abi::models::QueryBuilderError;
+use crate::abi::models::{AbiRequestContext, QueryBuilderError};
 use crate::abi::query_builder::AbiProvider;
+use crate::abi::rpc::fetch_transaction_and_receipt;
 
 pub enum ResultField {
     TxType(u8),
@@ -37,28 +39,110 @@ pub enum ResultField {
     CallDataField([u8; 32]),
 }
 
+/// RPC used by the tests that replay real Sepolia transactions, overridable through
+/// `CCNEXT_TEST_RPC_URL`.
+pub const DEFAULT_TEST_RPC_URL: &str = "https://sepolia-proxy-rpc.creditcoin.network";
+
 pub async fn get_transaction_and_receipt(tx_hash_str: &str) -> (Transaction, TransactionReceipt) {
-    // RPC.
-    let rpc_url = "https://sepolia-proxy-rpc.creditcoin.network";
-    let provider = ProviderBuilder::new().on_http(rpc_url.parse().unwrap());
+    let rpc_url =
+        std::env::var("CCNEXT_TEST_RPC_URL").unwrap_or_else(|_| DEFAULT_TEST_RPC_URL.into());
+    let provider = ProviderBuilder::new().on_http(rpc_url.parse().expect("valid rpc url"));
 
     // which transaction.
-    let tx_hash = B256::from_str(tx_hash_str).unwrap();
+    let tx_hash = B256::from_str(tx_hash_str).expect("valid transaction hash");
 
     // get the transaction & receipt.
-    let tx = provider
-        .get_transaction_by_hash(tx_hash)
-        .await
-        .unwrap()
-        .unwrap();
-    let rx = provider
-        .get_transaction_receipt(tx_hash)
+    fetch_transaction_and_receipt(&provider, tx_hash)
         .await
-        .unwrap()
-        .unwrap();
+        .unwrap_or_else(|e| panic!("failed to fetch {tx_hash_str} from {rpc_url}: {e:?}"))
+}
+
+/// Offline stand-in for a G-CRE `burn(uint256)` call on Sepolia, so tests that don't
+/// care about a specific on-chain transaction can run without an RPC.
+///
+/// The receipt holds two logs: `Burnt(from, value)` at index 0 and
+/// `Transfer(from, 0x0, value)` at index 1.
+pub fn get_local_transaction_and_receipt() -> (Transaction, TransactionReceipt) {
+    let (transaction, receipt) = get_local_transaction_and_receipt_json();
+    let tx: Transaction = serde_json::from_value(transaction).expect("valid transaction");
+    let rx: TransactionReceipt = serde_json::from_value(receipt).expect("valid receipt");
     (tx, rx)
 }
 
+/// Hash of the transaction returned by [`get_local_transaction_and_receipt`].
+pub const LOCAL_TRANSACTION_HASH: &str =
+    "0x5a1e0d2b3c4f5e6d7c8b9a0f1e2d3c4b5a69788796a5b4c3d2e1f00112233445";
+
+/// JSON-RPC shaped version of [`get_local_transaction_and_receipt`].
+pub fn get_local_transaction_and_receipt_json() -> (Value, Value) {
+    let transaction = json!({
+        "type": "0x0",
+        "chainId": "0xaa36a7",
+        "nonce": "0x2a",
+        "gasPrice": "0x3b9aca00",
+        "gas": "0x186a0",
+        "to": "0xac1d3d7a8878e655cbb063d58e453540641f4117",
+        "value": "0x0",
+        "input": "0x42966c680000000000000000000000000000000000000000000000000de0b6b3a7640000",
+        "r": "0x3b08715b4403c792b8c7567edea634088bedcd7f60d9352b1f16c69830f3afd5",
+        "s": "0x10b9afb67d2ec8b956f0e1dbc07eb79152904f3a7bf789fc869db56320adfe09",
+        "v": "0x1546d71",
+        "hash": "0x5a1e0d2b3c4f5e6d7c8b9a0f1e2d3c4b5a69788796a5b4c3d2e1f00112233445",
+        "blockHash": "0x8e38b4dbf6b11fcc3b9dee84fb7986e29ca0a02cecd8977c161ff7333329681e",
+        "blockNumber": "0x6c1a2b",
+        "transactionIndex": "0x3",
+        "from": "0x1f1e9426d6b7d100746b09956ad90a0796e19aa3"
+    });
+    let receipt = json!({
+        "blockHash": "0x8e38b4dbf6b11fcc3b9dee84fb7986e29ca0a02cecd8977c161ff7333329681e",
+        "blockNumber": "0x6c1a2b",
+        "contractAddress": null,
+        "cumulativeGasUsed": "0x1d4c0",
+        "effectiveGasPrice": "0x3b9aca00",
+        "from": "0x1f1e9426d6b7d100746b09956ad90a0796e19aa3",
+        "gasUsed": "0x9c40",
+        "logs": [
+            {
+                "address": "0xac1d3d7a8878e655cbb063d58e453540641f4117",
+                "topics": [
+                    "0x919f7e2092ffcc9d09f599be18d8152860b0c054df788a33bc549cdd9d0f15b1",
+                    "0x0000000000000000000000001f1e9426d6b7d100746b09956ad90a0796e19aa3"
+                ],
+                "data": "0x0000000000000000000000000000000000000000000000000de0b6b3a7640000",
+                "blockNumber": "0x6c1a2b",
+                "transactionHash": "0x5a1e0d2b3c4f5e6d7c8b9a0f1e2d3c4b5a69788796a5b4c3d2e1f00112233445",
+                "transactionIndex": "0x3",
+                "blockHash": "0x8e38b4dbf6b11fcc3b9dee84fb7986e29ca0a02cecd8977c161ff7333329681e",
+                "logIndex": "0x7",
+                "removed": false
+            },
+            {
+                "address": "0xac1d3d7a8878e655cbb063d58e453540641f4117",
+                "topics": [
+                    "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef",
+                    "0x0000000000000000000000001f1e9426d6b7d100746b09956ad90a0796e19aa3",
+                    "0x0000000000000000000000000000000000000000000000000000000000000000"
+                ],
+                "data": "0x0000000000000000000000000000000000000000000000000de0b6b3a7640000",
+                "blockNumber": "0x6c1a2b",
+                "transactionHash": "0x5a1e0d2b3c4f5e6d7c8b9a0f1e2d3c4b5a69788796a5b4c3d2e1f00112233445",
+                "transactionIndex": "0x3",
+                "blockHash": "0x8e38b4dbf6b11fcc3b9dee84fb7986e29ca0a02cecd8977c161ff7333329681e",
+                "logIndex": "0x8",
+                "removed": false
+            }
+        ],
+        "logsBloom": format!("0x{}", "0".repeat(512)),
+        "status": "0x1",
+        "to": "0xac1d3d7a8878e655cbb063d58e453540641f4117",
+        "transactionHash": "0x5a1e0d2b3c4f5e6d7c8b9a0f1e2d3c4b5a69788796a5b4c3d2e1f00112233445",
+        "transactionIndex": "0x3",
+        "type": "0x0"
+    });
+
+    (transaction, receipt)
+}
+
 pub fn check_results(
     expected_results: Vec<ResultField>,
     result_segments: Vec<(usize, usize)>,
@@ -148,7 +232,7 @@ pub struct TestAbiProvider();
 
 #[async_trait]
 impl AbiProvider for TestAbiProvider {
-    async fn get_abi(&self, _contract_address: String) -> Result<String, QueryBuilderError> {
+    async fn get_abi(&self, _context: &AbiRequestContext) -> Result<String, QueryBuilderError> {
         // hard coded G-CRE's ABI
         let json_str = r#"[{"constant":false,"inputs":[{"name":"tokenHolders","type":"address[]"},{"name":"amounts","type":"uint256[]"}],"name":"recordSales730Days","outputs":[],"payable":false,"stateMutability":"nonpayable","type":"function"},{"constant":true,"inputs":[],"name":"VestingStartDate","outputs":[{"name":"","type":"uint256"}],"payable":false,"stateMutability":"view","type":"function"},{"constant":true,"inputs":[],"name":"name","outputs":[{"name":"","type":"string"}],"payable":false,"stateMutability":"view","type":"function"},{"constant":false,"inputs":[{"name":"spender","type":"address"},{"name":"value","type":"uint256"}],"name":"approve","outputs":[{"name":"success","type":"bool"}],"payable":false,"stateMutability":"nonpayable","type":"function"},{"constant":true,"inputs":[{"name":"tokenHolder","type":"address"}],"name":"vestedBalanceOf","outputs":[{"name":"balance","type":"uint256"}],"payable":false,"stateMutability":"view","type":"function"},{"constant":true,"inputs":[],"name":"totalSupply","outputs":[{"name":"amount","type":"uint256"}],"payable":false,"stateMutability":"view","type":"function"},{"constant":true,"inputs":[{"name":"tokenHolder","type":"address"}],"name":"purchasedBalanceOf365Days","outputs":[{"name":"balance","type":"uint256"}],"payable":false,"stateMutability":"view","type":"function"},{"constant":false,"inputs":[{"name":"value","type":"uint256"},{"name":"sighash","type":"string"}],"name":"exchange","outputs":[{"name":"success","type":"bool"}],"payable":false,"stateMutability":"nonpayable","type":"function"},{"constant":false,"inputs":[{"name":"from","type":"address"},{"name":"to","type":"address"},{"name":"value","type":"uint256"}],"name":"transferFrom","outputs":[{"name":"success","type":"bool"}],"payable":false,"stateMutability":"nonpayable","type":"function"},{"constant":true,"inputs":[{"name":"tokenHolder","type":"address"}],"name":"vestedBalanceOf183Days","outputs":[{"name":"balance","type":"uint256"}],"payable":false,"stateMutability":"view","type":"function"},{"constant":true,"inputs":[],"name":"decimals","outputs":[{"name":"","type":"uint8"}],"payable":false,"stateMutability":"view","type":"function"},{"constant":false,"inputs":[{"name":"tokenHolders","type":"address[]"},{"name":"amounts","type":"uint256[]"}],"name":"recordSales1095Days","outputs":[],"payable":false,"stateMutability":"nonpayable","type":"function"},{"constant":true,"inputs":[{"name":"tokenHolder","type":"address"}],"name":"vestedBalanceOf365Days","outputs":[{"name":"balance","type":"uint256"}],"payable":false,"stateMutability":"view","type":"function"},{"constant":false,"inputs":[{"name":"value","type":"uint256"}],"name":"burn","outputs":[{"name":"success","type":"bool"}],"payable":false,"stateMutability":"nonpayable","type":"function"},{"constant":true,"inputs":[{"name":"tokenHolder","type":"address"}],"name":"purchasedBalanceOf2190Days","outputs":[{"name":"balance","type":"uint256"}],"payable":false,"stateMutability":"view","type":"function"},{"constant":false,"inputs":[{"name":"tokenHolders","type":"address[]"},{"name":"amounts","type":"uint256[]"}],"name":"recordSales183Days","outputs":[],"payable":false,"stateMutability":"nonpayable","type":"function"},{"constant":false,"inputs":[{"name":"tokenHolder","type":"address"},{"name":"numCoins","type":"uint256"}],"name":"recordSale365Days","outputs":[],"payable":false,"stateMutability":"nonpayable","type":"function"},{"constant":true,"inputs":[{"name":"tokenHolder","type":"address"}],"name":"vestedBalanceOf730Days","outputs":[{"name":"balance","type":"uint256"}],"payable":false,"stateMutability":"view","type":"function"},{"constant":true,"inputs":[{"name":"tokenHolder","type":"address"}],"name":"purchasedBalanceOf","outputs":[{"name":"balance","type":"uint256"}],"payable":false,"stateMutability":"view","type":"function"},{"constant":true,"inputs":[{"name":"owner","type":"address"}],"name":"balanceOf","outputs":[{"name":"balance","type":"uint256"}],"payable":false,"stateMutability":"view","type":"function"},{"constant":false,"inputs":[],"name":"finalizeSales","outputs":[],"payable":false,"stateMutability":"nonpayable","type":"function"},{"constant":false,"inputs":[{"name":"from","type":"address"},{"name":"value","type":"uint256"}],"name":"burnFrom","outputs":[{"name":"success","type":"bool"}],"payable":false,"stateMutability":"nonpayable","type":"function"},{"constant":true,"inputs":[{"name":"tokenHolder","type":"address"}],"name":"vestedBalanceOf2190Days","outputs":[{"name":"balance","type":"uint256"}],"payable":false,"stateMutability":"view","type":"function"},{"constant":true,"inputs":[{"name":"tokenHolder","type":"address"}],"name":"purchasedBalanceOf730Days","outputs":[{"name":"balance","type":"uint256"}],"payable":false,"stateMutability":"view","type":"function"},{"constant":true,"inputs":[],"name":"symbol","outputs":[{"name":"","type":"string"}],"payable":false,"stateMutability":"view","type":"function"},{"constant":false,"inputs":[{"name":"tokenHolder","type":"address"},{"name":"numCoins","type":"uint256"}],"name":"recordSale183Days","outputs":[],"payable":false,"stateMutability":"nonpayable","type":"function"},{"constant":false,"inputs":[{"name":"to","type":"address"},{"name":"value","type":"uint256"}],"name":"transfer","outputs":[{"name":"success","type":"bool"}],"payable":false,"stateMutability":"nonpayable","type":"function"},{"constant":true,"inputs":[],"name":"creditcoinSalesLimit","outputs":[{"name":"","type":"uint256"}],"payable":false,"stateMutability":"view","type":"function"},{"constant":true,"inputs":[{"name":"tokenHolder","type":"address"}],"name":"vestedBalanceOf1095Days","outputs":[{"name":"balance","type":"uint256"}],"payable":false,"stateMutability":"view","type":"function"},{"constant":true,"inputs":[],"name":"creditcoinLimitInFrac","outputs":[{"name":"","type":"uint256"}],"payable":false,"stateMutability":"view","type":"function"},{"constant":false,"inputs":[{"name":"tokenHolder","type":"address"},{"name":"numCoins","type":"uint256"}],"name":"recordSale2190Days","outputs":[],"payable":false,"stateMutability":"nonpayable","type":"function"},{"constant":false,"inputs":[{"name":"tokenHolder","type":"address"},{"name":"numCoins","type":"uint256"}],"name":"recordSale730Days","outputs":[],"payable":false,"stateMutability":"nonpayable","type":"function"},{"constant":true,"inputs":[{"name":"tokenHolder","type":"address"}],"name":"purchasedBalanceOf1095Days","outputs":[{"name":"balance","type":"uint256"}],"payable":false,"stateMutability":"view","type":"function"},{"constant":true,"inputs":[{"name":"owner","type":"address"},{"name":"spender","type":"address"}],"name":"allowance","outputs":[{"name":"remaining","type":"uint256"}],"payable":false,"stateMutability":"view","type":"function"},{"constant":false,"inputs":[],"name":"startVesting","outputs":[],"payable":false,"stateMutability":"nonpayable","type":"function"},{"constant":false,"inputs":[{"name":"tokenHolders","type":"address[]"},{"name":"amounts","type":"uint256[]"}],"name":"recordSales2190Days","outputs":[],"payable":false,"stateMutability":"nonpayable","type":"function"},{"constant":true,"inputs":[{"name":"tokenHolder","type":"address"}],"name":"purchasedBalanceOf183Days","outputs":[{"name":"balance","type":"uint256"}],"payable":false,"stateMutability":"view","type":"function"},{"constant":true,"inputs":[],"name":"IsSalesFinalized","outputs":[{"name":"","type":"bool"}],"payable":false,"stateMutability":"view","type":"function"},{"constant":false,"inputs":[{"name":"tokenHolders","type":"address[]"},{"name":"amounts","type":"uint256[]"}],"name":"recordSales365Days","outputs":[],"payable":false,"stateMutability":"nonpayable","type":"function"},{"constant":false,"inputs":[{"name":"tokenHolder","type":"address"},{"name":"numCoins","type":"uint256"}],"name":"recordSale1095Days","outputs":[],"payable":false,"stateMutability":"nonpayable","type":"function"},{"inputs":[{"name":"creditcoinFoundation","type":"address"},{"name":"devCost","type":"address"}],"payable":false,"stateMutability":"nonpayable","type":"constructor"},{"payable":true,"stateMutability":"payable","type":"fallback"},{"anonymous":false,"inputs":[{"indexed":true,"name":"from","type":"address"},{"indexed":false,"name":"value","type":"uint256"},{"indexed":true,"name":"sighash","type":"string"}],"name":"Exchange","type":"event"},{"anonymous":false,"inputs":[{"indexed":true,"name":"from","type":"address"},{"indexed":false,"name":"value","type":"uint256"}],"name":"Burnt","type":"event"},{"anonymous":false,"inputs":[{"indexed":true,"name":"from","type":"address"},{"indexed":true,"name":"to","type":"address"},{"indexed":false,"name":"value","type":"uint256"}],"name":"Transfer","type":"event"},{"anonymous":false,"inputs":[{"indexed":true,"name":"owner","type":"address"},{"indexed":true,"name":"spender","type":"address"},{"indexed":false,"name":"value","type":"uint256"}],"name":"Approval","type":"event"}]"#;
 
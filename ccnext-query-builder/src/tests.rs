@@ -1,13 +1,46 @@
 use crate::{
-    abi::{models::QueryableFields, query_builder::QueryBuilder},
+    abi::{
+        erc4337::{EntryPointVersion, IEntryPointV06, IEntryPointV07},
+        inline_abi::{sol_abi, IntoEventAbi},
+        log_filter::LogFilter,
+        models::{
+            AbiRequestContext, EventSearchMode, NestedCallTarget, NestedCalldata,
+            QueryBuilderError, QueryWarning, QueryableFields, SegmentPosition,
+        },
+        query_builder::{AbiProvider, QueryBuilder},
+        query_builder_for_constructor::QueryBuilderForConstructor,
+        query_builder_for_event::QueryBuilderForEvent,
+        query_builder_for_function::QueryBuilderForFunction,
+        query_spec::{QuerySpec, QuerySpecError},
+        registry::AbiRegistry,
+        signatures::SignatureDatabase,
+        typed::SolField,
+        utils::normalize_segments,
+    },
+    mock_transport::MockTransport,
+    sol_field,
     test_helpers::{
-        check_results, get_transaction_and_receipt, get_vrs, get_y_parity, ResultField,
-        TestAbiProvider,
+        check_results, get_local_transaction_and_receipt, get_local_transaction_and_receipt_json,
+        get_transaction_and_receipt, get_vrs, get_y_parity, ResultField, TestAbiProvider,
+        LOCAL_TRANSACTION_HASH,
     },
+    CcnextQuery,
 };
 
-use alloy::consensus::Transaction;
+use alloy::{
+    consensus::Transaction,
+    json_abi::{Event, Function},
+    primitives::{keccak256, Address, Bytes, B256, U256},
+    sol,
+    sol_types::{SolCall, SolEvent, SolValue},
+};
+use async_trait::async_trait;
 use ccnext_abi_encoding::{abi::abi_encode, common::EncodingVersion};
+use serde_json::Value;
+use std::{
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
 
 const ENCODING: EncodingVersion = EncodingVersion::V1;
 
@@ -405,3 +438,2109 @@ async fn type_4_tx_queried_fields_match_expected() {
     // Checking that all result data matches expected
     check_results(expected_results, selected_offsets, raw.clone());
 }
+
+#[test]
+fn normalize_segments_merges_overlapping_and_adjacent_ranges() {
+    let normalized =
+        normalize_segments(&[(64, 32), (0, 32), (32, 32), (64, 32), (200, 8), (70, 4)]);
+
+    assert_eq!(normalized.segments, vec![(0, 96), (200, 8)]);
+    assert_eq!(
+        normalized.selections,
+        vec![
+            SegmentPosition {
+                segment_index: 0,
+                offset_in_segment: 64,
+            },
+            SegmentPosition {
+                segment_index: 0,
+                offset_in_segment: 0,
+            },
+            SegmentPosition {
+                segment_index: 0,
+                offset_in_segment: 32,
+            },
+            SegmentPosition {
+                segment_index: 0,
+                offset_in_segment: 64,
+            },
+            SegmentPosition {
+                segment_index: 1,
+                offset_in_segment: 0,
+            },
+            SegmentPosition {
+                segment_index: 0,
+                offset_in_segment: 70,
+            },
+        ]
+    );
+}
+
+// Selecting the same Transfer log twice through `multi_event_builder` and `event_builder`
+// should collapse into the address + topics segment and the data segment.
+#[tokio::test]
+async fn normalized_offsets_deduplicate_repeated_event_selection() {
+    let (tx, rx) = get_local_transaction_and_receipt();
+    let encoded = abi_encode(tx.clone(), rx.clone(), ENCODING).unwrap();
+
+    let mut query_builder = QueryBuilder::create_from_transaction(tx.clone(), rx.clone(), ENCODING)
+        .expect("creating queryable builder should work");
+    query_builder.set_abi_provider(Box::new(TestAbiProvider()));
+
+    query_builder
+        .multi_event_builder(
+            "Transfer".into(),
+            |_log, _event, _log_index| true,
+            |builder| {
+                builder.add_signature()?.add_argument("from")?;
+                Ok(())
+            },
+        )
+        .await
+        .unwrap()
+        .event_builder(
+            "Transfer".into(),
+            |_log, _event, log_index| log_index == 1,
+            false,
+            |builder| {
+                builder
+                    .add_argument("from")?
+                    .add_argument("to")?
+                    .add_argument("value")?;
+                Ok(())
+            },
+        )
+        .await
+        .unwrap();
+
+    let selected_offsets = query_builder.get_selected_offsets();
+    let normalized = query_builder.get_normalized_offsets();
+    assert_eq!(selected_offsets.len(), 5);
+    assert_eq!(normalized.selections.len(), selected_offsets.len());
+    // signature + from + to are contiguous topics, the value lives in the data field.
+    assert_eq!(normalized.segments.len(), 2);
+    assert_eq!(normalized.segments[0].1, 3 * 32);
+
+    // signature, from, from again, to, value.
+    let expected_positions = [(0, 0), (0, 32), (0, 32), (0, 64), (1, 0)];
+    let positions: Vec<(usize, usize)> = normalized
+        .selections
+        .iter()
+        .map(|position| (position.segment_index, position.offset_in_segment))
+        .collect();
+    assert_eq!(positions, expected_positions);
+
+    let raw = encoded.abi();
+    let (topics_offset, _) = normalized.segments[0];
+    assert_eq!(
+        &raw[topics_offset..topics_offset + 32],
+        IBurnable::Transfer::SIGNATURE_HASH.as_slice()
+    );
+    assert_eq!(
+        &raw[topics_offset + 64..topics_offset + 96],
+        B256::ZERO.as_slice()
+    );
+    let (value_offset, value_size) = normalized.segments[1];
+    assert_eq!(value_size, 32);
+    assert_eq!(
+        &raw[value_offset..value_offset + 32],
+        U256::from(10).pow(U256::from(18)).to_be_bytes::<32>()
+    );
+}
+
+#[tokio::test]
+async fn json_query_spec_matches_equivalent_builder_calls() {
+    let (tx, rx) = get_local_transaction_and_receipt();
+
+    let spec = QuerySpec::from_json_str(
+        r#"{
+            "fields": ["RxStatus", "TxFrom"],
+            "function": { "name_or_signature": "burn", "signature": true, "arguments": ["value"] },
+            "events": [
+                {
+                    "name_or_signature": "Transfer",
+                    "filters": [{ "argument": "to", "equals": "0x0000000000000000000000000000000000000000" }],
+                    "address": true,
+                    "arguments": ["from", "value"]
+                }
+            ]
+        }"#,
+    )
+    .expect("spec should parse");
+
+    let mut from_spec = QueryBuilder::create_from_transaction(tx.clone(), rx.clone(), ENCODING)
+        .expect("creating queryable builder should work");
+    from_spec.set_abi_provider(Box::new(TestAbiProvider()));
+    from_spec
+        .apply_spec(&spec)
+        .await
+        .expect("spec should apply");
+
+    let mut by_hand = QueryBuilder::create_from_transaction(tx, rx, ENCODING)
+        .expect("creating queryable builder should work");
+    by_hand.set_abi_provider(Box::new(TestAbiProvider()));
+    by_hand
+        .add_static_field(QueryableFields::RxStatus)
+        .unwrap()
+        .add_static_field(QueryableFields::TxFrom)
+        .unwrap()
+        .function_builder("burn".into(), |b| {
+            b.add_signature()?.add_argument("value".into())?;
+            Ok(())
+        })
+        .await
+        .unwrap()
+        .event_builder(
+            "Transfer".into(),
+            |_log, _event, log_index| log_index == 1,
+            false,
+            |b| {
+                b.add_address()?
+                    .add_argument("from")?
+                    .add_argument("value")?;
+                Ok(())
+            },
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(
+        from_spec.get_selected_offsets(),
+        by_hand.get_selected_offsets()
+    );
+}
+
+#[tokio::test]
+async fn toml_query_spec_reports_precise_errors() {
+    let (tx, rx) = get_local_transaction_and_receipt();
+    let mut query_builder = QueryBuilder::create_from_transaction(tx, rx, ENCODING)
+        .expect("creating queryable builder should work");
+    query_builder.set_abi_provider(Box::new(TestAbiProvider()));
+
+    let spec = QuerySpec::from_toml_str(
+        r#"
+        [[events]]
+        name_or_signature = "Burnt"
+        signature = true
+
+        [[events]]
+        name_or_signature = "Transfer"
+        arguments = ["value"]
+        filters = [
+            { argument = "from", equals = "0x1f1e9426d6b7d100746b09956ad90a0796e19aa3" },
+            { argument = "amount", equals = "1" },
+        ]
+        "#,
+    )
+    .expect("spec should parse");
+
+    match query_builder.apply_spec(&spec).await {
+        Err(QueryBuilderError::InvalidQuerySpec(QuerySpecError::UnknownEventArgument {
+            path,
+            event,
+            argument,
+        })) => {
+            assert_eq!(path, "events[1].filters[1]");
+            assert_eq!(event, "Transfer");
+            assert_eq!(argument, "amount");
+        }
+        Err(other) => panic!("expected an unknown argument error, got {other:?}"),
+        Ok(_) => panic!("spec with an unknown argument should not apply"),
+    }
+
+    assert!(matches!(
+        QuerySpec::from_toml_str("[[events]]\nname = \"Transfer\""),
+        Err(QuerySpecError::FailedToParse(_))
+    ));
+}
+
+#[tokio::test]
+async fn failing_query_spec_leaves_the_selection_unchanged() {
+    let (tx, rx) = get_local_transaction_and_receipt();
+    let mut query_builder = QueryBuilder::create_from_transaction(tx, rx, ENCODING)
+        .expect("creating queryable builder should work");
+    query_builder.set_abi_provider(Box::new(TestAbiProvider()));
+    query_builder
+        .add_static_field(QueryableFields::TxFrom)
+        .unwrap();
+    let selected_offsets = query_builder.get_selected_offsets();
+
+    let spec = QuerySpec::from_json_str(
+        r#"{
+            "fields": ["RxStatus"],
+            "function": { "name_or_signature": "burn", "arguments": ["value"] },
+            "events": [
+                { "name_or_signature": "Burnt", "signature": true },
+                {
+                    "name_or_signature": "Transfer",
+                    "filters": [{ "argument": "value", "equals": "1" }],
+                    "arguments": ["value"]
+                }
+            ]
+        }"#,
+    )
+    .expect("spec should parse");
+
+    assert!(matches!(
+        query_builder.apply_spec(&spec).await,
+        Err(QueryBuilderError::InvalidQuerySpec(
+            QuerySpecError::NoMatchingEvent { path }
+        )) if path == "events[1]"
+    ));
+    assert_eq!(query_builder.get_selected_offsets(), selected_offsets);
+}
+
+#[tokio::test]
+async fn from_tx_hash_builds_the_same_query_as_create_from_transaction() {
+    let (tx_json, rx_json) = get_local_transaction_and_receipt_json();
+    let transport = MockTransport::default();
+    transport
+        .push_response("eth_getTransactionByHash", tx_json)
+        .push_response("eth_getTransactionReceipt", rx_json);
+
+    let tx_hash = B256::from_str(LOCAL_TRANSACTION_HASH).unwrap();
+    let mut from_hash = QueryBuilder::from_tx_hash(&transport.provider(), tx_hash, ENCODING)
+        .await
+        .expect("transaction should be fetched");
+    from_hash
+        .add_static_field(QueryableFields::TxFrom)
+        .unwrap()
+        .add_static_field(QueryableFields::RxLogBlooms)
+        .unwrap();
+
+    let (tx, rx) = get_local_transaction_and_receipt();
+    let mut from_transaction = QueryBuilder::create_from_transaction(tx, rx, ENCODING).unwrap();
+    from_transaction
+        .add_static_field(QueryableFields::TxFrom)
+        .unwrap()
+        .add_static_field(QueryableFields::RxLogBlooms)
+        .unwrap();
+
+    assert_eq!(
+        from_hash.get_selected_offsets(),
+        from_transaction.get_selected_offsets()
+    );
+    let requested: Vec<String> = transport.requests().into_iter().map(|r| r.0).collect();
+    assert_eq!(
+        requested,
+        vec!["eth_getTransactionByHash", "eth_getTransactionReceipt"]
+    );
+}
+
+#[tokio::test]
+async fn from_tx_hash_reports_missing_pending_and_mismatched_transactions() {
+    let tx_hash = B256::from_str(LOCAL_TRANSACTION_HASH).unwrap();
+    let (tx_json, rx_json) = get_local_transaction_and_receipt_json();
+
+    // not found.
+    let transport = MockTransport::default();
+    transport.push_response("eth_getTransactionByHash", Value::Null);
+    assert!(matches!(
+        QueryBuilder::from_tx_hash(&transport.provider(), tx_hash, ENCODING).await,
+        Err(QueryBuilderError::TransactionNotFound(hash)) if hash == tx_hash
+    ));
+
+    // pending, still in the mempool.
+    let mut pending_tx = tx_json.clone();
+    pending_tx["blockHash"] = Value::Null;
+    pending_tx["blockNumber"] = Value::Null;
+    pending_tx["transactionIndex"] = Value::Null;
+    let transport = MockTransport::default();
+    transport.push_response("eth_getTransactionByHash", pending_tx);
+    assert!(matches!(
+        QueryBuilder::from_tx_hash(&transport.provider(), tx_hash, ENCODING).await,
+        Err(QueryBuilderError::TransactionPending(hash)) if hash == tx_hash
+    ));
+
+    // mined, but the receipt isn't available yet.
+    let transport = MockTransport::default();
+    transport
+        .push_response("eth_getTransactionByHash", tx_json.clone())
+        .push_response("eth_getTransactionReceipt", Value::Null);
+    assert!(matches!(
+        QueryBuilder::from_tx_hash(&transport.provider(), tx_hash, ENCODING).await,
+        Err(QueryBuilderError::ReceiptNotFound(hash)) if hash == tx_hash
+    ));
+
+    // receipt from another block, e.g. after a reorg.
+    let mut reorged_rx = rx_json.clone();
+    reorged_rx["blockHash"] =
+        "0x1111111111111111111111111111111111111111111111111111111111111111".into();
+    let transport = MockTransport::default();
+    transport
+        .push_response("eth_getTransactionByHash", tx_json.clone())
+        .push_response("eth_getTransactionReceipt", reorged_rx);
+    assert!(matches!(
+        QueryBuilder::from_tx_hash(&transport.provider(), tx_hash, ENCODING).await,
+        Err(QueryBuilderError::ReceiptBlockHashMismatch { .. })
+    ));
+
+    // receipt of a different transaction.
+    let mut other_rx = rx_json;
+    other_rx["transactionHash"] =
+        "0x2222222222222222222222222222222222222222222222222222222222222222".into();
+    let transport = MockTransport::default();
+    transport
+        .push_response("eth_getTransactionByHash", tx_json)
+        .push_response("eth_getTransactionReceipt", other_rx);
+    assert!(matches!(
+        QueryBuilder::from_tx_hash(&transport.provider(), tx_hash, ENCODING).await,
+        Err(QueryBuilderError::ReceiptTransactionHashMismatch { expected, .. }) if expected == tx_hash
+    ));
+
+    // transport failure.
+    let transport = MockTransport::default();
+    assert!(matches!(
+        QueryBuilder::from_tx_hash(&transport.provider(), tx_hash, ENCODING).await,
+        Err(QueryBuilderError::ProviderRequestFailed(_))
+    ));
+}
+
+/// Records every request that actually reaches the wrapped [`TestAbiProvider`].
+#[derive(Clone, Default)]
+struct RecordingAbiProvider(Arc<Mutex<Vec<AbiRequestContext>>>);
+
+impl RecordingAbiProvider {
+    fn requests(&self) -> Vec<AbiRequestContext> {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl AbiProvider for RecordingAbiProvider {
+    async fn get_abi(&self, context: &AbiRequestContext) -> Result<String, QueryBuilderError> {
+        self.0.lock().unwrap().push(*context);
+        TestAbiProvider().get_abi(context).await
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn abi_registry_is_shared_across_builders_on_spawned_tasks() {
+    let provider = RecordingAbiProvider::default();
+    let registry = AbiRegistry::new(provider.clone());
+
+    // warm the registry first so every task below is a cache hit.
+    let (tx, _) = get_local_transaction_and_receipt();
+    let context = AbiRequestContext::new(tx.to().unwrap()).with_chain_id(11155111);
+    registry.get_abi(&context).await.unwrap();
+
+    let mut tasks = Vec::new();
+    for _ in 0..8 {
+        let registry = registry.clone();
+        // `tokio::spawn` only accepts `Send` futures, so this doubles as a compile time check.
+        tasks.push(tokio::spawn(async move {
+            let (tx, rx) = get_local_transaction_and_receipt();
+            let mut query_builder = QueryBuilder::create_from_transaction(tx, rx, ENCODING)
+                .expect("creating queryable builder should work");
+            query_builder.set_abi_registry(registry);
+
+            query_builder
+                .function_builder("burn".into(), |builder| {
+                    builder.add_argument("value".into())?;
+                    Ok(())
+                })
+                .await
+                .unwrap()
+                .event_builder(
+                    "Burnt".into(),
+                    |_log, _event, _log_index| true,
+                    false,
+                    |builder| {
+                        builder.add_argument("value")?;
+                        Ok(())
+                    },
+                )
+                .await
+                .unwrap();
+            query_builder.get_selected_offsets()
+        }));
+    }
+
+    let mut selections = Vec::new();
+    for task in tasks {
+        selections.push(task.await.unwrap());
+    }
+
+    assert!(selections.windows(2).all(|pair| pair[0] == pair[1]));
+    assert_eq!(selections[0].len(), 2);
+    assert_eq!(registry.len(), 1);
+    assert_eq!(provider.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn abi_requests_carry_chain_address_and_block_of_the_transaction() {
+    let (tx, rx) = get_local_transaction_and_receipt();
+    let provider = RecordingAbiProvider::default();
+
+    let mut query_builder = QueryBuilder::create_from_transaction(tx.clone(), rx, ENCODING)
+        .expect("creating queryable builder should work");
+    query_builder.set_abi_registry(AbiRegistry::new(provider.clone()));
+    query_builder
+        .function_builder("burn".into(), |builder| {
+            builder.add_argument("value".into())?;
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(
+        provider.requests(),
+        vec![AbiRequestContext {
+            chain_id: Some(11155111),
+            address: tx.to().unwrap(),
+            block_number: Some(0x6c1a2b),
+        }]
+    );
+
+    // an explicit chain id replaces the one of the transaction and is a separate cache entry.
+    query_builder.set_chain_id(1);
+    query_builder
+        .get_abi_from_provider_cached(tx.to().unwrap())
+        .await
+        .unwrap();
+    assert_eq!(provider.requests().len(), 2);
+    assert_eq!(provider.requests()[1].chain_id, Some(1));
+}
+
+/// Stands in for an explorer that has no verified source for any contract.
+struct UnverifiedAbiProvider();
+
+#[async_trait]
+impl AbiProvider for UnverifiedAbiProvider {
+    async fn get_abi(&self, context: &AbiRequestContext) -> Result<String, QueryBuilderError> {
+        Err(QueryBuilderError::NoAbiFoundForContract(
+            context.address.to_string(),
+        ))
+    }
+}
+
+async fn select_burn_and_transfer(
+    query_builder: &mut QueryBuilder,
+) -> Result<Vec<(usize, usize)>, QueryBuilderError> {
+    query_builder
+        .function_builder("burn".into(), |builder| {
+            builder.add_argument("value".into())?;
+            Ok(())
+        })
+        .await?
+        .event_builder(
+            "Transfer".into(),
+            |_log, _event, _log_index| true,
+            false,
+            |builder| {
+                builder.add_signature()?.add_argument("value")?;
+                Ok(())
+            },
+        )
+        .await?;
+    Ok(query_builder.get_selected_offsets())
+}
+
+#[tokio::test]
+async fn signature_database_stands_in_for_missing_abis() {
+    let (tx, rx) = get_local_transaction_and_receipt();
+
+    let mut with_abi = QueryBuilder::create_from_transaction(tx.clone(), rx.clone(), ENCODING)
+        .expect("creating queryable builder should work");
+    with_abi.set_abi_provider(Box::new(TestAbiProvider()));
+    let expected = select_burn_and_transfer(&mut with_abi).await.unwrap();
+
+    // `Burnt` isn't in the bundled database, so that log is skipped rather than failing.
+    let mut without_abi = QueryBuilder::create_from_transaction(tx.clone(), rx.clone(), ENCODING)
+        .expect("creating queryable builder should work");
+    without_abi.set_abi_provider(Box::new(UnverifiedAbiProvider()));
+    without_abi.set_signature_database(Arc::new(SignatureDatabase::bundled()));
+    assert_eq!(
+        select_burn_and_transfer(&mut without_abi).await.unwrap(),
+        expected
+    );
+
+    // without a database the missing ABI is still an error.
+    let mut no_database = QueryBuilder::create_from_transaction(tx, rx, ENCODING)
+        .expect("creating queryable builder should work");
+    no_database.set_abi_provider(Box::new(UnverifiedAbiProvider()));
+    assert!(matches!(
+        select_burn_and_transfer(&mut no_database).await,
+        Err(QueryBuilderError::NoAbiFoundForContract(_))
+    ));
+}
+
+#[tokio::test]
+async fn colliding_signatures_are_reported_as_ambiguous() {
+    let (tx, rx) = get_local_transaction_and_receipt();
+    let database =
+        SignatureDatabase::parse("function burn(uint256 value)\nfunction burn(uint256 amount)")
+            .unwrap();
+
+    let mut query_builder = QueryBuilder::create_from_transaction(tx, rx, ENCODING)
+        .expect("creating queryable builder should work");
+    query_builder.set_signature_database(Arc::new(database));
+
+    let error = query_builder
+        .function_builder("0x42966c68".into(), |builder| {
+            builder.add_argument("value".into())?;
+            Ok(())
+        })
+        .await
+        .err()
+        .unwrap();
+    match error {
+        QueryBuilderError::AmbiguousSignatureMatch {
+            selector,
+            candidates,
+        } => {
+            assert_eq!(selector, "0x42966c68");
+            assert_eq!(candidates.len(), 2);
+        }
+        e => panic!("unexpected error {e:?}"),
+    }
+}
+
+sol! {
+    #![sol(abi)]
+    interface IBurnable {
+        event Transfer(address indexed from, address indexed to, uint256 value);
+        event Burnt(address indexed, uint256);
+        function burn(uint256 value) returns (bool success);
+    }
+}
+
+fn select_burn_and_transfer_with_abi(event: impl IntoEventAbi) -> Vec<(usize, usize)> {
+    let (tx, rx) = get_local_transaction_and_receipt();
+    let mut query_builder = QueryBuilder::create_from_transaction(tx, rx, ENCODING)
+        .expect("creating queryable builder should work");
+
+    query_builder
+        .function_builder_with_abi(sol_abi::<IBurnable::burnCall>(), |builder| {
+            builder.add_argument("value".into())?;
+            Ok(())
+        })
+        .unwrap()
+        .event_builder_with_abi(
+            event,
+            |_log, _event, _log_index| true,
+            false,
+            |builder| {
+                builder.add_signature()?.add_argument("value")?;
+                Ok(())
+            },
+        )
+        .unwrap();
+    query_builder.get_selected_offsets()
+}
+
+#[tokio::test]
+async fn inline_abis_match_provider_abis() {
+    let (tx, rx) = get_local_transaction_and_receipt();
+    let mut with_provider = QueryBuilder::create_from_transaction(tx, rx, ENCODING)
+        .expect("creating queryable builder should work");
+    with_provider.set_abi_provider(Box::new(TestAbiProvider()));
+    let expected = select_burn_and_transfer(&mut with_provider).await.unwrap();
+
+    // none of these builders has an ABI provider.
+    let human_readable = "event Transfer(address indexed from, address indexed to, uint256 value)";
+    assert_eq!(select_burn_and_transfer_with_abi(human_readable), expected);
+    assert_eq!(
+        select_burn_and_transfer_with_abi(Event::parse(human_readable).unwrap()),
+        expected
+    );
+    assert_eq!(
+        select_burn_and_transfer_with_abi(sol_abi::<IBurnable::Transfer>()),
+        expected
+    );
+}
+
+#[test]
+fn inline_function_must_match_the_calldata_selector() {
+    let (tx, rx) = get_local_transaction_and_receipt();
+    let mut query_builder = QueryBuilder::create_from_transaction(tx, rx, ENCODING)
+        .expect("creating queryable builder should work");
+
+    let error = query_builder
+        .function_builder_with_abi("function burnFrom(address from, uint256 value)", |_| Ok(()))
+        .err()
+        .unwrap();
+    assert!(matches!(
+        error,
+        QueryBuilderError::FunctionDoesNotMatchCalldata { calldata_selector, .. }
+            if calldata_selector == "0x42966c68"
+    ));
+
+    assert!(matches!(
+        query_builder
+            .function_builder_with_abi("function burn(uint256", |_| Ok(()))
+            .err()
+            .unwrap(),
+        QueryBuilderError::FailedToParseHumanReadableAbi { .. }
+    ));
+}
+
+#[tokio::test]
+async fn typed_builders_match_string_builders() {
+    let (tx, rx) = get_local_transaction_and_receipt();
+    let mut with_provider = QueryBuilder::create_from_transaction(tx.clone(), rx.clone(), ENCODING)
+        .expect("creating queryable builder should work");
+    with_provider.set_abi_provider(Box::new(TestAbiProvider()));
+    select_burn_and_transfer(&mut with_provider).await.unwrap();
+    with_provider
+        .event_builder(
+            "Burnt".into(),
+            |_log, _event, _log_index| true,
+            false,
+            |builder| {
+                builder.add_argument("from")?.add_argument("value")?;
+                Ok(())
+            },
+        )
+        .await
+        .unwrap();
+    let expected = with_provider.get_selected_offsets();
+
+    let mut query_builder = QueryBuilder::create_from_transaction(tx, rx, ENCODING)
+        .expect("creating queryable builder should work");
+    query_builder
+        .function_builder_typed::<IBurnable::burnCall, _>(|builder| {
+            builder.add_argument(sol_field!(IBurnable::burnCall, value))?;
+            Ok(())
+        })
+        .unwrap()
+        .event_builder_typed::<IBurnable::Transfer, _, _>(
+            |_log, transfer: IBurnable::Transfer, _log_index| transfer.to.is_zero(),
+            false,
+            |builder| {
+                builder
+                    .add_signature()?
+                    .add_argument(sol_field!(IBurnable::Transfer, value))?;
+                Ok(())
+            },
+        )
+        .unwrap()
+        // unnamed parameters are `_0`, `_1`, ... in the generated struct.
+        .event_builder_typed::<IBurnable::Burnt, _, _>(
+            |_log, _burnt, _log_index| true,
+            false,
+            |builder| {
+                builder
+                    .add_argument(sol_field!(IBurnable::Burnt, _0))?
+                    .add_argument(sol_field!(IBurnable::Burnt, _1))?;
+                Ok(())
+            },
+        )
+        .unwrap();
+    assert_eq!(query_builder.get_selected_offsets(), expected);
+
+    // the typed filter sees the decoded event.
+    assert!(matches!(
+        query_builder
+            .event_builder_typed::<IBurnable::Transfer, _, _>(
+                |_log, transfer: IBurnable::Transfer, _log_index| !transfer.to.is_zero(),
+                false,
+                |_| Ok(()),
+            )
+            .err()
+            .unwrap(),
+        QueryBuilderError::FailedToFindEventByNameOrSignature(_)
+    ));
+
+    // fields built without `sol_field!` may not be arguments of the event.
+    match query_builder
+        .event_builder_typed::<IBurnable::Transfer, _, _>(
+            |_log, _transfer, _log_index| true,
+            true,
+            |builder| {
+                builder.add_argument(SolField::new_unchecked("amount"))?;
+                Ok(())
+            },
+        )
+        .err()
+        .unwrap()
+    {
+        QueryBuilderError::UnknownEventArgument { event, argument } => {
+            assert_eq!(event, "Transfer");
+            assert_eq!(argument, "amount");
+        }
+        e => panic!("unexpected error {e:?}"),
+    }
+}
+
+#[derive(CcnextQuery, Debug, PartialEq)]
+struct BurnQuery {
+    #[field(RxStatus)]
+    status: bool,
+    #[field(TxFrom)]
+    sender: Address,
+    #[function("burn", arg = "value")]
+    amount: U256,
+    #[event(
+        "event Transfer(address indexed from, address indexed to, uint256 value)",
+        arg = "to"
+    )]
+    recipient: Address,
+    #[event("Burnt", signature)]
+    burnt_topic: B256,
+    #[event("Transfer", address, log_index = 1)]
+    token: Address,
+}
+
+#[tokio::test]
+async fn derived_query_round_trips_through_the_encoding() {
+    let (tx, rx) = get_local_transaction_and_receipt();
+    let encoded = abi_encode(tx.clone(), rx.clone(), ENCODING).unwrap();
+
+    let mut query_builder = QueryBuilder::create_from_transaction(tx.clone(), rx.clone(), ENCODING)
+        .expect("creating queryable builder should work");
+    query_builder.set_abi_provider(Box::new(TestAbiProvider()));
+    let segments = BurnQuery::build_query(&mut query_builder).await.unwrap();
+    assert_eq!(segments, query_builder.get_selected_offsets());
+
+    let burn_log = &rx.inner.logs()[0];
+    assert_eq!(
+        BurnQuery::decode_query(encoded.abi(), &segments).unwrap(),
+        BurnQuery {
+            status: true,
+            sender: tx.from,
+            amount: U256::from_be_slice(&tx.input()[4..]),
+            recipient: Address::ZERO,
+            burnt_topic: burn_log.topics()[0],
+            token: tx.to().unwrap(),
+        }
+    );
+
+    assert!(matches!(
+        BurnQuery::decode_query(encoded.abi(), &segments[1..]),
+        Err(QueryBuilderError::MissMatchedLengthDecoding)
+    ));
+}
+
+/// Answers every request with the same ABI.
+struct StaticAbiProvider(&'static str);
+
+#[async_trait]
+impl AbiProvider for StaticAbiProvider {
+    async fn get_abi(&self, _context: &AbiRequestContext) -> Result<String, QueryBuilderError> {
+        Ok(self.0.into())
+    }
+}
+
+#[tokio::test]
+async fn function_overloads_are_resolved_by_the_calldata_selector() {
+    let (tx, rx) = get_local_transaction_and_receipt();
+    let mut expected_builder =
+        QueryBuilder::create_from_transaction(tx.clone(), rx.clone(), ENCODING)
+            .expect("creating queryable builder should work");
+    expected_builder.set_abi_provider(Box::new(TestAbiProvider()));
+    expected_builder
+        .function_builder("burn".into(), |builder| {
+            builder.add_argument("value".into())?;
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+    let overloaded = r#"[
+        {"type":"function","name":"burn","inputs":[{"name":"from","type":"address"},{"name":"value","type":"uint256"}],"outputs":[],"stateMutability":"nonpayable"},
+        {"type":"function","name":"burn","inputs":[{"name":"value","type":"uint256"}],"outputs":[],"stateMutability":"nonpayable"},
+        {"type":"function","name":"transfer","inputs":[{"name":"to","type":"address"},{"name":"value","type":"uint256"}],"outputs":[],"stateMutability":"nonpayable"}
+    ]"#;
+    let mut query_builder = QueryBuilder::create_from_transaction(tx, rx, ENCODING)
+        .expect("creating queryable builder should work");
+    query_builder.set_abi_provider(Box::new(StaticAbiProvider(overloaded)));
+    query_builder
+        .function_builder("burn".into(), |builder| {
+            builder.add_argument("value".into())?;
+            Ok(())
+        })
+        .await
+        .unwrap();
+    assert_eq!(
+        query_builder.get_selected_offsets(),
+        expected_builder.get_selected_offsets()
+    );
+
+    // neither by name nor by selector can another function be queried.
+    for name_or_signature in ["transfer", "0xa9059cbb"] {
+        let error = query_builder
+            .function_builder(name_or_signature.into(), |_| Ok(()))
+            .await
+            .err()
+            .unwrap();
+        match error {
+            QueryBuilderError::FunctionDoesNotMatchCalldata {
+                function,
+                calldata_selector,
+            } => {
+                assert_eq!(function, "function transfer(address to, uint256 value)");
+                assert_eq!(calldata_selector, "0x42966c68");
+            }
+            e => panic!("unexpected error {e:?}"),
+        }
+    }
+}
+
+#[tokio::test]
+async fn signatures_and_fragments_resolve_like_selectors() {
+    let (tx, rx) = get_local_transaction_and_receipt();
+    let mut query_builder = QueryBuilder::create_from_transaction(tx, rx, ENCODING)
+        .expect("creating queryable builder should work");
+    query_builder.set_abi_provider(Box::new(TestAbiProvider()));
+
+    let transfer_topic = "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef";
+    let by_topic = query_builder
+        .find_all_events(transfer_topic.into(), |_log, _event, _log_index| true)
+        .await
+        .unwrap();
+    assert_eq!(by_topic.len(), 1);
+
+    for signature in [
+        "Transfer(address,address,uint256)",
+        "event Transfer(address indexed from, address indexed to, uint256 value)",
+    ] {
+        let events = query_builder
+            .find_all_events(signature.into(), |_log, _event, _log_index| true)
+            .await
+            .unwrap();
+        assert_eq!(events, by_topic);
+    }
+
+    for signature in [
+        "0x42966c68",
+        "burn(uint256)",
+        "function burn(uint256 value) returns (bool)",
+    ] {
+        query_builder
+            .function_builder(signature.into(), |builder| {
+                builder.add_argument("value".into())?;
+                Ok(())
+            })
+            .await
+            .unwrap();
+    }
+    let selected_offsets = query_builder.get_selected_offsets();
+    assert!(selected_offsets.windows(2).all(|pair| pair[0] == pair[1]));
+
+    assert!(matches!(
+        query_builder
+            .function_builder("burn(uint256".into(), |_| Ok(()))
+            .await
+            .err()
+            .unwrap(),
+        QueryBuilderError::FailedToParseHumanReadableAbi { .. }
+    ));
+}
+
+#[tokio::test]
+async fn anonymous_events_are_matched_by_address_and_topic_count() {
+    let (transaction, mut receipt) = get_local_transaction_and_receipt_json();
+    let mut swept_log = receipt["logs"][1].clone();
+    swept_log["address"] = "0x73f7b1184B5cD361cC0f7654998953E2a251dd58".into();
+    swept_log["topics"] =
+        serde_json::json!(["0x00000000000000000000000000000000000000000000000000000000000000aa"]);
+    swept_log["logIndex"] = "0x9".into();
+    receipt["logs"].as_array_mut().unwrap().push(swept_log);
+    let tx: alloy::rpc::types::Transaction = serde_json::from_value(transaction).unwrap();
+    let rx: alloy::rpc::types::TransactionReceipt = serde_json::from_value(receipt).unwrap();
+    let encoded = abi_encode(tx.clone(), rx.clone(), ENCODING).unwrap();
+
+    let swept = "event Swept(address indexed to, uint256 amount) anonymous";
+    let sweeper = Address::from_str("0x73f7b1184B5cD361cC0f7654998953E2a251dd58").unwrap();
+    let mut query_builder = QueryBuilder::create_from_transaction(tx.clone(), rx, ENCODING)
+        .expect("creating queryable builder should work");
+    query_builder
+        .event_builder_at_address(
+            sweeper,
+            swept,
+            |_, _, _| true,
+            false,
+            |builder| {
+                builder.add_argument("to")?.add_argument("amount")?;
+                Ok(())
+            },
+        )
+        .unwrap();
+
+    // the only indexed argument is topic 0.
+    let raw = encoded.abi();
+    let selected: Vec<&[u8]> = query_builder
+        .get_selected_offsets()
+        .into_iter()
+        .map(|(offset, size)| &raw[offset..offset + size])
+        .collect();
+    assert_eq!(
+        selected[0],
+        Address::with_last_byte(0xaa).into_word().as_slice()
+    );
+    assert_eq!(
+        selected[1],
+        U256::from(10).pow(U256::from(18)).to_be_bytes::<32>()
+    );
+
+    // the same shape at the token address, which logs nothing with a single topic.
+    assert!(matches!(
+        query_builder
+            .event_builder_at_address(tx.to().unwrap(), swept, |_, _, _| true, false, |_| Ok(()))
+            .err()
+            .unwrap(),
+        QueryBuilderError::FailedToFindEventByNameOrSignature(_)
+    ));
+    assert!(matches!(
+        query_builder
+            .event_builder_with_abi(swept, |_, _, _| true, false, |_| Ok(()))
+            .err()
+            .unwrap(),
+        QueryBuilderError::AnonymousEventNeedsAddress(_)
+    ));
+    assert!(matches!(
+        query_builder
+            .event_builder_at_address(
+                sweeper,
+                swept,
+                |_, _, _| true,
+                false,
+                |builder| {
+                    builder.add_signature()?;
+                    Ok(())
+                }
+            )
+            .err()
+            .unwrap(),
+        QueryBuilderError::AnonymousEventHasNoSignature(_)
+    ));
+}
+
+struct TokenOnlyAbiProvider(Address);
+
+#[async_trait]
+impl AbiProvider for TokenOnlyAbiProvider {
+    async fn get_abi(&self, context: &AbiRequestContext) -> Result<String, QueryBuilderError> {
+        if context.address == self.0 {
+            TestAbiProvider().get_abi(context).await
+        } else {
+            UnverifiedAbiProvider().get_abi(context).await
+        }
+    }
+}
+
+#[tokio::test]
+async fn tolerant_event_search_skips_logs_of_unverified_contracts() {
+    let (transaction, mut receipt) = get_local_transaction_and_receipt_json();
+    let mut unverified_log = receipt["logs"][1].clone();
+    unverified_log["address"] = "0x73f7b1184B5cD361cC0f7654998953E2a251dd58".into();
+    unverified_log["logIndex"] = "0x9".into();
+    receipt["logs"].as_array_mut().unwrap().push(unverified_log);
+    let tx: alloy::rpc::types::Transaction = serde_json::from_value(transaction).unwrap();
+    let rx: alloy::rpc::types::TransactionReceipt = serde_json::from_value(receipt).unwrap();
+
+    let mut query_builder = QueryBuilder::create_from_transaction(tx.clone(), rx, ENCODING)
+        .expect("creating queryable builder should work");
+    query_builder.set_abi_provider(Box::new(TokenOnlyAbiProvider(tx.to().unwrap())));
+    assert!(matches!(
+        query_builder
+            .find_all_events("Transfer".into(), |_, _, _| true)
+            .await
+            .err()
+            .unwrap(),
+        QueryBuilderError::NoAbiFoundForContract(_)
+    ));
+    assert!(query_builder.get_warnings().is_empty());
+
+    query_builder.set_event_search_mode(EventSearchMode::Tolerant);
+    let transfers = query_builder
+        .find_all_events("Transfer".into(), |_, _, _| true)
+        .await
+        .unwrap();
+    assert_eq!(transfers.len(), 1);
+    assert_eq!(transfers[0].2, 1);
+
+    let by_signature = query_builder
+        .find_all_events("Transfer(address,address,uint256)".into(), |_, _, _| true)
+        .await
+        .unwrap();
+    assert_eq!(by_signature.len(), 1);
+
+    let warnings = query_builder.get_warnings();
+    assert_eq!(warnings.len(), 2);
+    for warning in warnings {
+        let QueryWarning::SkippedLog {
+            log_index,
+            address,
+            error,
+        } = warning;
+        assert_eq!(*log_index, 2);
+        assert_eq!(
+            *address,
+            Address::from_str("0x73f7b1184B5cD361cC0f7654998953E2a251dd58").unwrap()
+        );
+        assert!(matches!(error, QueryBuilderError::NoAbiFoundForContract(_)));
+    }
+}
+
+#[tokio::test]
+async fn tolerant_event_search_skips_logs_with_colliding_signatures() {
+    let (transaction, mut receipt) = get_local_transaction_and_receipt_json();
+    let mut unverified_log = receipt["logs"][1].clone();
+    unverified_log["address"] = "0x73f7b1184B5cD361cC0f7654998953E2a251dd58".into();
+    unverified_log["logIndex"] = "0x9".into();
+    receipt["logs"].as_array_mut().unwrap().push(unverified_log);
+    let tx: alloy::rpc::types::Transaction = serde_json::from_value(transaction).unwrap();
+    let rx: alloy::rpc::types::TransactionReceipt = serde_json::from_value(receipt).unwrap();
+    let database = SignatureDatabase::parse(
+        "event Transfer(address indexed from, address indexed to, uint256 value)\n\
+         event Transfer(address indexed src, address indexed dst, uint256 wad)",
+    )
+    .unwrap();
+
+    let mut query_builder = QueryBuilder::create_from_transaction(tx.clone(), rx, ENCODING)
+        .expect("creating queryable builder should work");
+    query_builder.set_abi_provider(Box::new(TokenOnlyAbiProvider(tx.to().unwrap())));
+    query_builder.set_signature_database(Arc::new(database));
+    for event_name_or_signature in ["Transfer", "Transfer(address,address,uint256)"] {
+        assert!(matches!(
+            query_builder
+                .find_all_events(event_name_or_signature.into(), |_, _, _| true)
+                .await
+                .err()
+                .unwrap(),
+            QueryBuilderError::AmbiguousSignatureMatch { .. }
+        ));
+    }
+    assert!(query_builder.get_warnings().is_empty());
+
+    query_builder.set_event_search_mode(EventSearchMode::Tolerant);
+    for event_name_or_signature in ["Transfer", "Transfer(address,address,uint256)"] {
+        let transfers = query_builder
+            .find_all_events(event_name_or_signature.into(), |_, _, _| true)
+            .await
+            .unwrap();
+        assert_eq!(transfers.len(), 1);
+        assert_eq!(transfers[0].2, 1);
+    }
+
+    let warnings = query_builder.get_warnings();
+    assert_eq!(warnings.len(), 2);
+    for warning in warnings {
+        let QueryWarning::SkippedLog {
+            log_index, error, ..
+        } = warning;
+        assert_eq!(*log_index, 2);
+        match error {
+            QueryBuilderError::AmbiguousSignatureMatch { candidates, .. } => {
+                assert_eq!(candidates.len(), 2)
+            }
+            e => panic!("unexpected error {e:?}"),
+        }
+    }
+}
+
+sol! {
+    #![sol(abi)]
+    interface IWallet {
+        function execTransaction(address to, uint256 value, bytes data, uint8 operation) returns (bool success);
+        function multicall(bytes[] data) returns (bytes[] results);
+    }
+}
+
+struct WalletAbiProvider(Address);
+
+#[async_trait]
+impl AbiProvider for WalletAbiProvider {
+    async fn get_abi(&self, context: &AbiRequestContext) -> Result<String, QueryBuilderError> {
+        if context.address == self.0 {
+            Ok(serde_json::to_string(&IWallet::abi::contract()).unwrap())
+        } else {
+            TestAbiProvider().get_abi(context).await
+        }
+    }
+}
+
+/// The local burn transaction, sent through a wallet contract with `input` as calldata.
+fn wallet_transaction_and_receipt(
+    wallet: Address,
+    input: Vec<u8>,
+) -> (
+    alloy::rpc::types::Transaction,
+    alloy::rpc::types::TransactionReceipt,
+) {
+    let (mut transaction, mut receipt) = get_local_transaction_and_receipt_json();
+    transaction["to"] = wallet.to_string().into();
+    transaction["input"] = format!("0x{}", hex::encode(input)).into();
+    receipt["to"] = wallet.to_string().into();
+    (
+        serde_json::from_value(transaction).unwrap(),
+        serde_json::from_value(receipt).unwrap(),
+    )
+}
+
+#[tokio::test]
+async fn nested_calldata_is_selected_at_absolute_offsets() {
+    let wallet = Address::from_str("0x73f7b1184B5cD361cC0f7654998953E2a251dd58").unwrap();
+    let token = Address::from_str("0xAc1D3D7A8878E655cBb063D58E453540641f4117").unwrap();
+    let burned = U256::from(10).pow(U256::from(18));
+    let burn_calldata = IBurnable::burnCall { value: burned }.abi_encode();
+    let select_burn = |builder: &mut QueryBuilderForFunction| {
+        builder.add_signature()?.add_argument("value".into())?;
+        Ok(())
+    };
+
+    // Safe like execTransaction, the inner target is its `to` argument.
+    let exec_transaction = IWallet::execTransactionCall {
+        to: token,
+        value: U256::ZERO,
+        data: burn_calldata.clone().into(),
+        operation: 0,
+    }
+    .abi_encode();
+    let (tx, rx) = wallet_transaction_and_receipt(wallet, exec_transaction);
+    let raw = abi_encode(tx.clone(), rx.clone(), ENCODING)
+        .unwrap()
+        .abi()
+        .to_vec();
+
+    let mut query_builder = QueryBuilder::create_from_transaction(tx.clone(), rx.clone(), ENCODING)
+        .expect("creating queryable builder should work");
+    query_builder.set_abi_provider(Box::new(WalletAbiProvider(wallet)));
+    query_builder
+        .nested_function_builder(
+            "execTransaction".into(),
+            NestedCalldata::new("data").with_target(NestedCallTarget::Argument("to".into())),
+            "burn".into(),
+            select_burn,
+        )
+        .await
+        .unwrap();
+    let selected: Vec<&[u8]> = query_builder
+        .get_selected_offsets()
+        .into_iter()
+        .map(|(offset, size)| &raw[offset..offset + size])
+        .collect();
+    assert_eq!(selected[0], IBurnable::burnCall::SELECTOR);
+    assert_eq!(selected[1], burned.to_be_bytes::<32>());
+
+    let mut inline_builder = QueryBuilder::create_from_transaction(tx, rx, ENCODING)
+        .expect("creating queryable builder should work");
+    inline_builder
+        .function_builder_with_abi(sol_abi::<IWallet::execTransactionCall>(), |builder| {
+            builder.nested_function_builder_with_abi(
+                &NestedCalldata::new("data"),
+                "function burn(uint256 value)",
+                select_burn,
+            )?;
+            Ok(())
+        })
+        .unwrap();
+    assert_eq!(
+        inline_builder.get_selected_offsets(),
+        query_builder.get_selected_offsets()
+    );
+    assert!(matches!(
+        inline_builder
+            .function_builder_with_abi(sol_abi::<IWallet::execTransactionCall>(), |builder| {
+                builder.nested_function_builder_with_abi(
+                    &NestedCalldata::new("to"),
+                    "function burn(uint256 value)",
+                    |_| Ok(()),
+                )?;
+                Ok(())
+            })
+            .err()
+            .unwrap(),
+        QueryBuilderError::ArgumentIsNotNestedCalldata(_)
+    ));
+
+    // multicall, the inner calls are elements of a `bytes[]` argument.
+    let multicall = IWallet::multicallCall {
+        data: vec![vec![0xde, 0xad].into(), burn_calldata.into()],
+    }
+    .abi_encode();
+    let (tx, rx) = wallet_transaction_and_receipt(wallet, multicall);
+    let raw = abi_encode(tx.clone(), rx.clone(), ENCODING)
+        .unwrap()
+        .abi()
+        .to_vec();
+
+    let mut query_builder = QueryBuilder::create_from_transaction(tx, rx, ENCODING)
+        .expect("creating queryable builder should work");
+    query_builder.set_abi_provider(Box::new(WalletAbiProvider(wallet)));
+    query_builder
+        .nested_function_builder(
+            "multicall".into(),
+            NestedCalldata::new("data")
+                .with_element(1)
+                .with_target(NestedCallTarget::Address(token)),
+            "burn".into(),
+            select_burn,
+        )
+        .await
+        .unwrap();
+    let selected: Vec<&[u8]> = query_builder
+        .get_selected_offsets()
+        .into_iter()
+        .map(|(offset, size)| &raw[offset..offset + size])
+        .collect();
+    assert_eq!(selected[0], IBurnable::burnCall::SELECTOR);
+    assert_eq!(selected[1], burned.to_be_bytes::<32>());
+
+    assert!(matches!(
+        query_builder
+            .nested_function_builder(
+                "multicall".into(),
+                NestedCalldata::new("data")
+                    .with_element(2)
+                    .with_target(NestedCallTarget::Address(token)),
+                "burn".into(),
+                select_burn,
+            )
+            .await
+            .err()
+            .unwrap(),
+        QueryBuilderError::MissingDataInCalldataOffsets
+    ));
+}
+
+#[tokio::test]
+async fn user_operations_are_selected_with_their_events() {
+    let version = EntryPointVersion::V07;
+    let entry_point = version.address();
+    let token = Address::from_str("0xAc1D3D7A8878E655cBb063D58E453540641f4117").unwrap();
+    let burned = U256::from(10).pow(U256::from(18));
+    let user_operation =
+        |sender: Address, nonce: u64, call_data: Vec<u8>| IEntryPointV07::PackedUserOperation {
+            sender,
+            nonce: U256::from(nonce),
+            initCode: Default::default(),
+            callData: call_data.into(),
+            accountGasLimits: B256::with_last_byte(1),
+            preVerificationGas: U256::from(21000),
+            gasFees: B256::with_last_byte(2),
+            paymasterAndData: Default::default(),
+            signature: vec![0x5e; 65].into(),
+        };
+    let execute = |data: Vec<u8>| {
+        [
+            Function::parse("function execute(address dest, uint256 value, bytes func)")
+                .unwrap()
+                .selector()
+                .as_slice(),
+            &(token, U256::ZERO, alloy::primitives::Bytes::from(data)).abi_encode_params(),
+        ]
+        .concat()
+    };
+    let handle_ops = IEntryPointV07::handleOpsCall {
+        ops: vec![
+            user_operation(Address::with_last_byte(1), 7, execute(vec![])),
+            user_operation(
+                Address::with_last_byte(2),
+                9,
+                execute(IBurnable::burnCall { value: burned }.abi_encode()),
+            ),
+        ],
+        beneficiary: Address::with_last_byte(3),
+    }
+    .abi_encode();
+
+    let (mut transaction, mut receipt) = get_local_transaction_and_receipt_json();
+    transaction["to"] = entry_point.to_string().into();
+    transaction["input"] = format!("0x{}", hex::encode(&handle_ops)).into();
+    receipt["to"] = entry_point.to_string().into();
+    let hashes = version
+        .user_operation_hashes(&handle_ops, entry_point, 11155111)
+        .unwrap();
+    for (op_index, hash) in hashes.iter().enumerate() {
+        let mut user_operation_log = receipt["logs"][1].clone();
+        user_operation_log["address"] = entry_point.to_string().into();
+        user_operation_log["topics"] = serde_json::json!([
+            version.user_operation_event().selector(),
+            hash,
+            Address::with_last_byte(op_index as u8 + 1).into_word(),
+            B256::ZERO,
+        ]);
+        let nonce = U256::from([7, 9][op_index]);
+        user_operation_log["data"] = format!(
+            "0x{}",
+            hex::encode((nonce, op_index == 1, U256::from(1), U256::from(2)).abi_encode_params())
+        )
+        .into();
+        user_operation_log["logIndex"] = format!("0x{:x}", 2 + op_index).into();
+        receipt["logs"]
+            .as_array_mut()
+            .unwrap()
+            .push(user_operation_log);
+    }
+    let tx: alloy::rpc::types::Transaction = serde_json::from_value(transaction).unwrap();
+    let rx: alloy::rpc::types::TransactionReceipt = serde_json::from_value(receipt).unwrap();
+    let raw = abi_encode(tx.clone(), rx.clone(), ENCODING)
+        .unwrap()
+        .abi()
+        .to_vec();
+
+    let mut query_builder = QueryBuilder::create_from_transaction(tx, rx, ENCODING)
+        .expect("creating queryable builder should work");
+    assert_eq!(
+        query_builder.user_operation_hash(version, 1).unwrap(),
+        hashes[1]
+    );
+    query_builder
+        .user_operation_builder(version, 1, |user_operation| {
+            user_operation
+                .add_sender()?
+                .add_nonce()?
+                .call_data_builder(
+                    "function execute(address dest, uint256 value, bytes func)",
+                    |execute| {
+                        execute
+                            .add_argument("dest".into())?
+                            .nested_function_builder_with_abi(
+                                &NestedCalldata::new("func"),
+                                sol_abi::<IBurnable::burnCall>(),
+                                |burn| {
+                                    burn.add_argument("value".into())?;
+                                    Ok(())
+                                },
+                            )?;
+                        Ok(())
+                    },
+                )?;
+            Ok(())
+        })
+        .unwrap()
+        .user_operation_event_builder(version, 1, |event| {
+            event.add_argument("nonce")?.add_argument("success")?;
+            Ok(())
+        })
+        .unwrap();
+
+    let selected: Vec<&[u8]> = query_builder
+        .get_selected_offsets()
+        .into_iter()
+        .map(|(offset, size)| &raw[offset..offset + size])
+        .collect();
+    assert_eq!(
+        selected[0],
+        Address::with_last_byte(2).into_word().as_slice()
+    );
+    assert_eq!(selected[1], U256::from(9).to_be_bytes::<32>());
+    assert_eq!(selected[2], token.into_word().as_slice());
+    assert_eq!(selected[3], burned.to_be_bytes::<32>());
+    assert_eq!(selected[4], U256::from(9).to_be_bytes::<32>());
+    assert_eq!(selected[5], U256::from(1).to_be_bytes::<32>());
+
+    assert!(matches!(
+        query_builder
+            .user_operation_builder(version, 2, |_| Ok(()))
+            .err()
+            .unwrap(),
+        QueryBuilderError::UserOperationNotFound(2)
+    ));
+    assert!(matches!(
+        query_builder
+            .user_operation_builder(EntryPointVersion::V06, 0, |_| Ok(()))
+            .err()
+            .unwrap(),
+        QueryBuilderError::FunctionDoesNotMatchCalldata { .. }
+    ));
+}
+
+#[tokio::test]
+async fn constructor_arguments_are_selected_after_the_creation_bytecode() {
+    let bytecode =
+        hex::decode("6080604052348015600f57600080fd5b50603f80601d6000396000f3fe").unwrap();
+    let owner = Address::with_last_byte(0x0e);
+    let supply = U256::from(21_000_000);
+    let arguments = ("Creditcoin".to_string(), owner, supply).abi_encode_params();
+    let artifact = serde_json::json!({
+        "abi": [{
+            "type": "constructor",
+            "stateMutability": "nonpayable",
+            "inputs": [
+                {"name": "name", "type": "string"},
+                {"name": "owner", "type": "address"},
+                {"name": "supply", "type": "uint256"}
+            ]
+        }],
+        "bytecode": {"object": format!("0x{}", hex::encode(&bytecode))}
+    })
+    .to_string();
+
+    let (mut transaction, mut receipt) = get_local_transaction_and_receipt_json();
+    transaction["to"] = Value::Null;
+    transaction["input"] = format!("0x{}{}", hex::encode(&bytecode), hex::encode(arguments)).into();
+    receipt["to"] = Value::Null;
+    receipt["contractAddress"] = "0x73f7b1184B5cD361cC0f7654998953E2a251dd58".into();
+    let tx: alloy::rpc::types::Transaction = serde_json::from_value(transaction).unwrap();
+    let rx: alloy::rpc::types::TransactionReceipt = serde_json::from_value(receipt).unwrap();
+    let raw = abi_encode(tx.clone(), rx.clone(), ENCODING)
+        .unwrap()
+        .abi()
+        .to_vec();
+
+    let select_arguments = |builder: &mut QueryBuilderForConstructor| {
+        builder
+            .add_argument("name".into())?
+            .add_argument("owner".into())?
+            .add_argument("supply".into())?;
+        Ok(())
+    };
+    let mut query_builder = QueryBuilder::create_from_transaction(tx.clone(), rx.clone(), ENCODING)
+        .expect("creating queryable builder should work");
+    query_builder
+        .constructor_builder_from_artifact(&artifact, select_arguments)
+        .unwrap();
+    let selected: Vec<&[u8]> = query_builder
+        .get_selected_offsets()
+        .into_iter()
+        .map(|(offset, size)| &raw[offset..offset + size])
+        .collect();
+    assert_eq!(selected[0], b"Creditcoin");
+    assert_eq!(selected[1], owner.into_word().as_slice());
+    assert_eq!(selected[2], supply.to_be_bytes::<32>());
+
+    let mut provider_builder = QueryBuilder::create_from_transaction(tx.clone(), rx, ENCODING)
+        .expect("creating queryable builder should work");
+    provider_builder.set_abi_provider(Box::new(StaticAbiProvider(
+        r#"[{"type":"constructor","stateMutability":"nonpayable","inputs":[{"name":"name","type":"string"},{"name":"owner","type":"address"},{"name":"supply","type":"uint256"}]}]"#,
+    )));
+    provider_builder
+        .constructor_builder(bytecode.len(), select_arguments)
+        .await
+        .unwrap()
+        .constructor_builder_with_abi(
+            "constructor(string name, address owner, uint256 supply)",
+            bytecode.len(),
+            select_arguments,
+        )
+        .unwrap();
+    assert_eq!(
+        provider_builder.get_selected_offsets(),
+        [
+            query_builder.get_selected_offsets(),
+            query_builder.get_selected_offsets()
+        ]
+        .concat()
+    );
+
+    let other_artifact = artifact.replace("6080604052", "6080604053");
+    assert!(matches!(
+        query_builder
+            .constructor_builder_from_artifact(&other_artifact, select_arguments)
+            .err()
+            .unwrap(),
+        QueryBuilderError::CreationBytecodeDoesNotMatch
+    ));
+
+    let (tx, rx) = get_local_transaction_and_receipt();
+    let mut call_builder = QueryBuilder::create_from_transaction(tx, rx, ENCODING)
+        .expect("creating queryable builder should work");
+    assert!(matches!(
+        call_builder
+            .constructor_builder_from_artifact(&artifact, select_arguments)
+            .err()
+            .unwrap(),
+        QueryBuilderError::NotAContractCreation
+    ));
+}
+
+#[test]
+fn raw_selections_match_abi_selections() {
+    let (tx, rx) = get_local_transaction_and_receipt();
+    let mut expected_builder =
+        QueryBuilder::create_from_transaction(tx.clone(), rx.clone(), ENCODING)
+            .expect("creating queryable builder should work");
+    expected_builder
+        .function_builder_with_abi(sol_abi::<IBurnable::burnCall>(), |builder| {
+            builder.add_signature()?.add_argument("value".into())?;
+            Ok(())
+        })
+        .unwrap()
+        .event_builder_with_abi(
+            sol_abi::<IBurnable::Transfer>(),
+            |_, _, _| true,
+            false,
+            |builder| {
+                builder
+                    .add_address()?
+                    .add_signature()?
+                    .add_argument("from")?
+                    .add_argument("to")?
+                    .add_argument("value")?;
+                Ok(())
+            },
+        )
+        .unwrap();
+
+    let mut raw_builder = QueryBuilder::create_from_transaction(tx, rx, ENCODING)
+        .expect("creating queryable builder should work");
+    raw_builder
+        .add_calldata_range(0, 4)
+        .unwrap()
+        .add_calldata_range(4, 32)
+        .unwrap()
+        .raw_log_builder(1, |builder| {
+            builder
+                .add_address()?
+                .add_topic(0)?
+                .add_topic(1)?
+                .add_topic(2)?
+                .add_data_range(0, 32)?;
+            Ok(())
+        })
+        .unwrap();
+    assert_eq!(
+        raw_builder.get_selected_offsets(),
+        expected_builder.get_selected_offsets()
+    );
+
+    assert!(matches!(
+        raw_builder.add_calldata_range(4, 33).err().unwrap(),
+        QueryBuilderError::RangeOutOfBounds {
+            start: 4,
+            size: 33,
+            field_size: 36
+        }
+    ));
+    assert!(matches!(
+        raw_builder
+            .function_builder_with_abi(sol_abi::<IBurnable::burnCall>(), |builder| {
+                builder.add_calldata_range(usize::MAX, 2)?;
+                Ok(())
+            })
+            .err()
+            .unwrap(),
+        QueryBuilderError::RangeOutOfBounds { .. }
+    ));
+    assert!(matches!(
+        raw_builder
+            .raw_log_builder(1, |builder| {
+                builder.add_topic(3)?;
+                Ok(())
+            })
+            .err()
+            .unwrap(),
+        QueryBuilderError::TopicIndexOutOfBounds {
+            topic_index: 3,
+            topic_count: 3
+        }
+    ));
+    assert!(matches!(
+        raw_builder
+            .raw_log_builder(1, |builder| {
+                builder.add_data_range(1, 32)?;
+                Ok(())
+            })
+            .err()
+            .unwrap(),
+        QueryBuilderError::RangeOutOfBounds { .. }
+    ));
+    assert!(matches!(
+        raw_builder
+            .raw_log_builder(1, |builder| {
+                builder.add_argument("value")?;
+                Ok(())
+            })
+            .err()
+            .unwrap(),
+        QueryBuilderError::RawLogHasNoEvent
+    ));
+    assert!(matches!(
+        raw_builder.raw_log_builder(2, |_| Ok(())).err().unwrap(),
+        QueryBuilderError::MissingLogInAbiOffsets(2)
+    ));
+}
+
+#[test]
+fn whole_logs_and_log_count_prove_an_event_was_not_emitted() {
+    let (tx, rx) = get_local_transaction_and_receipt();
+    let raw = abi_encode(tx.clone(), rx.clone(), ENCODING)
+        .unwrap()
+        .abi()
+        .to_vec();
+    let mut query_builder = QueryBuilder::create_from_transaction(tx, rx.clone(), ENCODING)
+        .expect("creating queryable builder should work");
+    query_builder
+        .add_log_count()
+        .unwrap()
+        .add_all_log_signatures()
+        .unwrap()
+        .event_builder_with_abi(
+            sol_abi::<IBurnable::Transfer>(),
+            |_, _, _| true,
+            false,
+            |builder| {
+                builder.add_log()?;
+                Ok(())
+            },
+        )
+        .unwrap()
+        .raw_log_builder(0, |builder| {
+            builder.add_log()?;
+            Ok(())
+        })
+        .unwrap();
+    let selected: Vec<&[u8]> = query_builder
+        .get_selected_offsets()
+        .into_iter()
+        .map(|(offset, size)| &raw[offset..offset + size])
+        .collect();
+    assert_eq!(selected.len(), 5);
+
+    // the count and every topic 0: no `Slashed` among them.
+    let logs = rx.inner.logs();
+    assert_eq!(selected[0], U256::from(logs.len()).to_be_bytes::<32>());
+    let slashed = Event::parse("event Slashed(address indexed validator, uint256 amount)")
+        .unwrap()
+        .selector();
+    for (log, signature) in logs.iter().zip(&selected[1..3]) {
+        assert_eq!(*signature, log.topic0().unwrap().as_slice());
+        assert_ne!(*signature, slashed.as_slice());
+    }
+
+    // whole logs decode on their own.
+    for (log, whole_log) in [&logs[1], &logs[0]].into_iter().zip(&selected[3..]) {
+        let (address, topics, data) =
+            <(Address, Vec<B256>, alloy::primitives::Bytes)>::abi_decode_params(whole_log, true)
+                .unwrap();
+        assert_eq!(address, log.address());
+        assert_eq!(topics, log.topics());
+        assert_eq!(data, log.data().data);
+    }
+}
+
+#[test]
+fn logs_without_topics_add_their_topic_count_to_log_signatures() {
+    let (transaction, mut receipt) = get_local_transaction_and_receipt_json();
+    let mut anonymous_log = receipt["logs"][0].clone();
+    anonymous_log["topics"] = serde_json::json!([]);
+    anonymous_log["logIndex"] = "0x9".into();
+    receipt["logs"].as_array_mut().unwrap().push(anonymous_log);
+    let tx: alloy::rpc::types::Transaction = serde_json::from_value(transaction).unwrap();
+    let rx: alloy::rpc::types::TransactionReceipt = serde_json::from_value(receipt).unwrap();
+    let raw = abi_encode(tx.clone(), rx.clone(), ENCODING)
+        .unwrap()
+        .abi()
+        .to_vec();
+
+    let mut query_builder = QueryBuilder::create_from_transaction(tx, rx.clone(), ENCODING)
+        .expect("creating queryable builder should work");
+    query_builder
+        .add_log_count()
+        .unwrap()
+        .add_all_log_signatures()
+        .unwrap()
+        .raw_log_builder(2, |builder| {
+            builder.add_log()?;
+            Ok(())
+        })
+        .unwrap();
+    let selected_offsets = query_builder.get_selected_offsets();
+    let selected: Vec<&[u8]> = selected_offsets
+        .iter()
+        .map(|(offset, size)| &raw[*offset..offset + size])
+        .collect();
+
+    // one segment per log, the anonymous one selecting its zero topic count.
+    assert_eq!(selected[0], U256::from(3).to_be_bytes::<32>());
+    let logs = rx.inner.logs();
+    assert_eq!(selected[1], logs[0].topic0().unwrap().as_slice());
+    assert_eq!(selected[2], logs[1].topic0().unwrap().as_slice());
+    assert_eq!(selected[3], [0u8; 32]);
+
+    // the topic count follows the head of the log: address, topics and data offsets.
+    let (log_offset, _) = selected_offsets[4];
+    let (address, topics, _) =
+        <(Address, Vec<B256>, alloy::primitives::Bytes)>::abi_decode_params(selected[4], true)
+            .unwrap();
+    assert_eq!(address, logs[2].address());
+    assert!(topics.is_empty());
+    assert_eq!(selected_offsets[3].0, log_offset + 3 * 32);
+}
+
+#[tokio::test]
+async fn receipt_abis_are_returned_next_to_per_address_failures() {
+    let (transaction, mut receipt) = get_local_transaction_and_receipt_json();
+    let unverified = Address::from_str("0x73f7b1184B5cD361cC0f7654998953E2a251dd58").unwrap();
+    let mut unverified_log = receipt["logs"][1].clone();
+    unverified_log["address"] = unverified.to_string().into();
+    unverified_log["logIndex"] = "0x9".into();
+    receipt["logs"].as_array_mut().unwrap().push(unverified_log);
+    let tx: alloy::rpc::types::Transaction = serde_json::from_value(transaction).unwrap();
+    let rx: alloy::rpc::types::TransactionReceipt = serde_json::from_value(receipt).unwrap();
+    let token = tx.to().unwrap();
+
+    let mut query_builder = QueryBuilder::create_from_transaction(tx, rx, ENCODING)
+        .expect("creating queryable builder should work");
+    assert!(matches!(
+        query_builder.get_receipt_abis().await,
+        Err(QueryBuilderError::AbiProviderNotInitialized)
+    ));
+
+    query_builder.set_abi_provider(Box::new(TokenOnlyAbiProvider(token)));
+    let batch = query_builder.get_receipt_abis().await.unwrap();
+    assert_eq!(batch.abis.keys().collect::<Vec<_>>(), vec![&token]);
+    assert!(batch.abis[&token].event("Transfer").is_some());
+    assert!(matches!(
+        batch.failures.as_slice(),
+        [(address, QueryBuilderError::NoAbiFoundForContract(_))] if *address == unverified
+    ));
+}
+
+#[test]
+fn user_operation_hashes_match_known_answers() {
+    use alloy::primitives::{address, b256, bytes};
+
+    // expected hashes computed outside this crate from the packing of the EntryPoint's
+    // `UserOperationLib` and `getUserOpHash`, so a change to the packing here is caught.
+    let sender = address!("6E0A5725dD4071e46356bD974E13F35DbF9ef367");
+    let execute = bytes!("b61d27f6000000000000000000000000ac1d3d7a8878e655cbb063d58e453540641f4117000000000000000000000000000000000000000000000000000000000000000100000000000000000000000000000000000000000000000000000000000000600000000000000000000000000000000000000000000000000000000000000000");
+
+    let v06 = IEntryPointV06::handleOpsCall {
+        ops: vec![IEntryPointV06::UserOperation {
+            sender,
+            nonce: U256::from(0x24),
+            initCode: Default::default(),
+            callData: execute.clone(),
+            callGasLimit: U256::from(70000),
+            verificationGasLimit: U256::from(100000),
+            preVerificationGas: U256::from(46580),
+            maxFeePerGas: U256::from(1500000030),
+            maxPriorityFeePerGas: U256::from(1500000000),
+            paymasterAndData: Default::default(),
+            signature: vec![0x11; 65].into(),
+        }],
+        beneficiary: Address::with_last_byte(3),
+    }
+    .abi_encode();
+    assert_eq!(
+        EntryPointVersion::V06
+            .user_operation_hashes(&v06, EntryPointVersion::V06.address(), 1)
+            .unwrap(),
+        [b256!(
+            "5d8b487588fc9f121bc5f9e7a8ddf9ba9aaf72051347cf1bc251671df7c94e14"
+        )]
+    );
+
+    let v07 = IEntryPointV07::handleOpsCall {
+        ops: vec![IEntryPointV07::PackedUserOperation {
+            sender,
+            nonce: (U256::from(0x2a) << 64) | U256::from(3),
+            initCode: bytes!("91e60e0613810449d098b0b5ec8b51a0fe8c89855fbfb9cf0000000000000000000000006e0a5725dd4071e46356bd974e13f35dbf9ef3670000000000000000000000000000000000000000000000000000000000000000"),
+            callData: execute,
+            // verification gas limit then call gas limit, 16 bytes each.
+            accountGasLimits: b256!(
+                "000000000000000000000000000186a000000000000000000000000000011170"
+            ),
+            preVerificationGas: U256::from(46580),
+            // max priority fee then max fee, 16 bytes each.
+            gasFees: b256!("00000000000000000000000059682f0000000000000000000000000059682f1e"),
+            paymasterAndData: bytes!("0000000000325602a77416a16136fdafd04b299f0000000000000000000000000000c35000000000000000000000000000004e20abcd"),
+            signature: vec![0x22; 65].into(),
+        }],
+        beneficiary: Address::with_last_byte(3),
+    }
+    .abi_encode();
+    assert_eq!(
+        EntryPointVersion::V07
+            .user_operation_hashes(&v07, EntryPointVersion::V07.address(), 11155111)
+            .unwrap(),
+        [b256!(
+            "049ab5b94c16c8944bee666ab9918d0fa429211040be88f9f8dc886da25b7141"
+        )]
+    );
+}
+
+sol! {
+    #![sol(abi)]
+    interface IUniversalRouter {
+        function execute(bytes commands, bytes[] inputs, uint256 deadline) payable;
+    }
+}
+
+#[test]
+fn router_inputs_are_selected_as_nested_params() {
+    let router = Address::from_str("0x3fC91A3afd70395Cd496C647d5a6CC9D4B2b7FAD").unwrap();
+    let token = Address::from_str("0xAc1D3D7A8878E655cBb063D58E453540641f4117").unwrap();
+    let amount_in = U256::from(10).pow(U256::from(18));
+    // token in, 0.3% fee tier, token out.
+    let path: Vec<u8> = [token.as_slice(), &[0x00, 0x0b, 0xb8], &[0xee; 20]].concat();
+    let swap_params =
+        "address recipient, uint256 amountIn, uint256 amountOutMin, bytes path, bool payerIsUser";
+
+    // WRAP_ETH then V3_SWAP_EXACT_IN, each input holding only its encoded parameters.
+    let execute = IUniversalRouter::executeCall {
+        commands: vec![0x0b, 0x00].into(),
+        inputs: vec![
+            (Address::with_last_byte(2), amount_in)
+                .abi_encode_params()
+                .into(),
+            (
+                Address::with_last_byte(1),
+                amount_in,
+                U256::from(1234),
+                alloy::primitives::Bytes::from(path.clone()),
+                false,
+            )
+                .abi_encode_params()
+                .into(),
+        ],
+        deadline: U256::from(1_700_000_000),
+    }
+    .abi_encode();
+    let (tx, rx) = wallet_transaction_and_receipt(router, execute);
+    let raw = abi_encode(tx.clone(), rx.clone(), ENCODING)
+        .unwrap()
+        .abi()
+        .to_vec();
+
+    let mut query_builder = QueryBuilder::create_from_transaction(tx, rx, ENCODING)
+        .expect("creating queryable builder should work");
+    query_builder
+        .function_builder_with_abi(sol_abi::<IUniversalRouter::executeCall>(), |execute| {
+            execute.nested_params_builder(
+                &NestedCalldata::new("inputs").with_element(1),
+                swap_params,
+                |swap| {
+                    swap.add_argument("amountIn".into())?
+                        .add_argument("path".into())?;
+                    Ok(())
+                },
+            )?;
+            Ok(())
+        })
+        .unwrap();
+    let selected: Vec<&[u8]> = query_builder
+        .get_selected_offsets()
+        .into_iter()
+        .map(|(offset, size)| &raw[offset..offset + size])
+        .collect();
+    assert_eq!(selected, [&amount_in.to_be_bytes::<32>()[..], &path[..]]);
+
+    let open_swap = |query_builder: &mut QueryBuilder, params: &str| {
+        query_builder
+            .function_builder_with_abi(sol_abi::<IUniversalRouter::executeCall>(), |execute| {
+                execute.nested_params_builder(
+                    &NestedCalldata::new("inputs").with_element(1),
+                    params,
+                    |swap| {
+                        swap.add_signature()?;
+                        Ok(())
+                    },
+                )?;
+                Ok(())
+            })
+            .err()
+            .unwrap()
+    };
+    assert!(matches!(
+        open_swap(&mut query_builder, "(address recipient, uint256 amountIn"),
+        QueryBuilderError::FailedToParseHumanReadableAbi { .. }
+    ));
+}
+
+#[tokio::test]
+async fn log_filters_match_like_the_closures_they_replace() {
+    let (transaction, mut receipt) = get_local_transaction_and_receipt_json();
+    let recipient = "0x73f7b1184B5cD361cC0f7654998953E2a251dd58";
+    let mut second_transfer = receipt["logs"][1].clone();
+    second_transfer["topics"][2] =
+        "0x00000000000000000000000073f7b1184b5cd361cc0f7654998953e2a251dd58".into();
+    second_transfer["data"] =
+        "0x0000000000000000000000000000000000000000000000001bc16d674ec80000".into();
+    second_transfer["logIndex"] = "0x9".into();
+    receipt["logs"]
+        .as_array_mut()
+        .unwrap()
+        .push(second_transfer);
+    let tx: alloy::rpc::types::Transaction = serde_json::from_value(transaction).unwrap();
+    let rx: alloy::rpc::types::TransactionReceipt =
+        serde_json::from_value(receipt.clone()).unwrap();
+    let token = tx.to().unwrap();
+
+    let create_query_builder = || {
+        let mut query_builder =
+            QueryBuilder::create_from_transaction(tx.clone(), rx.clone(), ENCODING)
+                .expect("creating queryable builder should work");
+        query_builder.set_abi_provider(Box::new(TestAbiProvider()));
+        query_builder
+    };
+
+    let mut query_builder = create_query_builder();
+    let cases = [
+        (LogFilter::new(), vec![1, 2]),
+        (LogFilter::new().with_address(token), vec![1, 2]),
+        (LogFilter::new().with_address(vec![Address::ZERO]), vec![]),
+        (
+            LogFilter::new().with_address(Vec::<Address>::new()),
+            vec![1, 2],
+        ),
+        (LogFilter::new().with_topic(2, B256::ZERO), vec![1]),
+        (LogFilter::new().with_argument("to", recipient), vec![2]),
+        (
+            LogFilter::new().with_argument("value", "1000000000000000000"),
+            vec![1],
+        ),
+        (LogFilter::new().with_log_index_range(2..=5), vec![2]),
+        (LogFilter::new().with_log_index_range(0..=1), vec![1]),
+        (LogFilter::new().with_log_index_range(3..=5), vec![]),
+        (LogFilter::new().with_nth(0), vec![1]),
+        (LogFilter::new().with_nth(1), vec![2]),
+        (LogFilter::new().with_address(token).with_nth(2), vec![]),
+        // nth counts among the logs passing the other criteria.
+        (
+            LogFilter::new().with_log_index_range(2..=5).with_nth(0),
+            vec![2],
+        ),
+        (
+            LogFilter::new().with_argument("to", recipient).with_nth(0),
+            vec![2],
+        ),
+    ];
+    for (filter, expected) in cases {
+        let log_indexes: Vec<usize> = query_builder
+            .find_all_events("Transfer".into(), filter.clone())
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|(_, _, log_index, _)| log_index)
+            .collect();
+        assert_eq!(log_indexes, expected, "{filter:?}");
+    }
+
+    // bad arguments fail whatever the other criteria.
+    assert!(matches!(
+        query_builder
+            .find_all_events(
+                "Transfer".into(),
+                LogFilter::new()
+                    .with_address(Address::ZERO)
+                    .with_argument("missing", "1"),
+            )
+            .await,
+        Err(QueryBuilderError::UnknownEventArgument { .. })
+    ));
+    assert!(matches!(
+        query_builder
+            .find_all_events(
+                "Transfer".into(),
+                LogFilter::new().with_argument("value", "one ether"),
+            )
+            .await,
+        Err(QueryBuilderError::InvalidArgumentFilterValue { .. })
+    ));
+    // arguments are checked and serialized in name order.
+    let bad_arguments = LogFilter::new()
+        .with_argument("value", "one ether")
+        .with_argument("amount", "1")
+        .with_argument("to", "nobody");
+    for _ in 0..4 {
+        match query_builder
+            .find_all_events("Transfer".into(), bad_arguments.clone())
+            .await
+        {
+            Err(QueryBuilderError::UnknownEventArgument { argument, .. }) => {
+                assert_eq!(argument, "amount")
+            }
+            other => panic!("unexpected result {other:?}"),
+        }
+    }
+    assert_eq!(
+        serde_json::to_string(&bad_arguments.arguments).unwrap(),
+        r#"{"amount":"1","to":"nobody","value":"one ether"}"#
+    );
+
+    let filter: LogFilter = serde_json::from_value(serde_json::json!({
+        "address": [token],
+        "topics": [null, null, "0x00000000000000000000000073f7b1184b5cd361cc0f7654998953e2a251dd58"],
+        "fromLogIndex": 1,
+    }))
+    .unwrap();
+    assert_eq!(
+        serde_json::from_value::<LogFilter>(serde_json::to_value(&filter).unwrap()).unwrap(),
+        filter
+    );
+
+    let select_value = |builder: &mut QueryBuilderForEvent| -> Result<(), QueryBuilderError> {
+        builder.add_argument("value")?;
+        Ok(())
+    };
+    let mut with_closures = create_query_builder();
+    with_closures
+        .event_builder(
+            "Transfer".into(),
+            |_, _, log_index| log_index == 2,
+            false,
+            select_value,
+        )
+        .await
+        .unwrap()
+        .multi_event_builder("Transfer".into(), |_, _, _| true, select_value)
+        .await
+        .unwrap();
+    query_builder
+        .event_builder("Transfer".into(), filter, false, select_value)
+        .await
+        .unwrap()
+        .multi_event_builder("Transfer".into(), LogFilter::new(), select_value)
+        .await
+        .unwrap();
+    assert_eq!(
+        query_builder.get_selected_offsets(),
+        with_closures.get_selected_offsets()
+    );
+    assert_eq!(query_builder.get_selected_offsets().len(), 3);
+
+    // indexed dynamic arguments are matched by their plain value.
+    let named =
+        Event::parse("event Named(string indexed name, bytes indexed data, uint256[] indexed ids)")
+            .unwrap();
+    let first_named_log_index = receipt["logs"].as_array().unwrap().len();
+    for (name, data, ids) in [("alice", "0x1234", [1u8, 2]), ("bob", "0x", [3, 4])] {
+        let mut log = receipt["logs"][1].clone();
+        log["topics"] = serde_json::json!([
+            named.selector(),
+            keccak256(name),
+            keccak256(data.parse::<Bytes>().unwrap()),
+            keccak256([U256::from(ids[0]), U256::from(ids[1])].abi_encode_packed()),
+        ]);
+        log["data"] = "0x".into();
+        receipt["logs"].as_array_mut().unwrap().push(log);
+    }
+    let rx: alloy::rpc::types::TransactionReceipt = serde_json::from_value(receipt).unwrap();
+    let mut query_builder = QueryBuilder::create_from_transaction(tx, rx, ENCODING)
+        .expect("creating queryable builder should work");
+
+    let cases = [
+        (LogFilter::new().with_argument("name", "alice"), vec![0]),
+        (LogFilter::new().with_argument("name", "bob"), vec![1]),
+        (LogFilter::new().with_argument("data", "0x1234"), vec![0]),
+        (LogFilter::new().with_argument("ids", "[3, 4]"), vec![1]),
+        (
+            LogFilter::new().with_argument("name", keccak256("alice").to_string()),
+            vec![],
+        ),
+        (
+            LogFilter::new()
+                .with_argument("name", "alice")
+                .with_argument("ids", "[3, 4]"),
+            vec![],
+        ),
+    ];
+    for (filter, expected) in cases {
+        let log_indexes: Vec<usize> = query_builder
+            .find_all_events_with_abi(&named, filter.clone())
+            .unwrap()
+            .into_iter()
+            .map(|(_, _, log_index, _)| log_index - first_named_log_index)
+            .collect();
+        assert_eq!(log_indexes, expected, "{filter:?}");
+    }
+    query_builder
+        .event_builder_at_address(
+            token,
+            &named,
+            LogFilter::new().with_argument("name", "bob"),
+            false,
+            |builder| {
+                builder.add_argument("name")?;
+                Ok(())
+            },
+        )
+        .unwrap();
+    assert_eq!(query_builder.get_selected_offsets().len(), 1);
+}